@@ -1,44 +1,42 @@
 use std::{
     io::prelude::*,
+    process::Command,
     time::{Duration, Instant},
 };
 
-use conpty::io::PipeReader;
+use conpty::PtySession;
 
 fn main() {
-    let mut p = conpty::spawn(r#"cmd"#).unwrap();
+    let mut session = PtySession::spawn(Command::new("cmd")).unwrap();
+    session.blocking(false);
 
-    println!("Process PID={:?}", p.pid());
+    println!("Process PID={:?}", session.pid());
 
-    let mut input = p.input().unwrap();
-    let mut output = p.output().unwrap();
-    output.blocking(false);
+    println!("{:?}", wait_for(&mut session, "All rights reserved"));
 
-    println!("{:?}", wait_for(&mut output, "All rights reserved"));
-
-    input
+    session
         .write_all("echo \"This is a test string 😁\"\r\n".as_bytes())
         .unwrap();
 
-    println!("{:?}", wait_for(&mut output, "😁"));
+    println!("{:?}", wait_for(&mut session, "😁"));
 
-    input.write_all(b"powershell\r\n").unwrap();
+    session.write_all(b"powershell\r\n").unwrap();
 
-    println!("{:?}", wait_for(&mut output, "https://aka.ms/PSWindows"));
+    println!("{:?}", wait_for(&mut session, "https://aka.ms/PSWindows"));
 
-    input.write_all(b"cat examples/cat.rs\r\n").unwrap();
+    session.write_all(b"cat examples/cat.rs\r\n").unwrap();
 
-    println!("{:?}", wait_for(&mut output, "main"));
+    println!("{:?}", wait_for(&mut session, "main"));
 }
 
-fn wait_for(output: &mut PipeReader, s: &str) -> String {
+fn wait_for(session: &mut PtySession, s: &str) -> String {
     let treashhold = Duration::from_secs(2);
     let now = Instant::now();
 
     let mut out = vec![0; 1000];
     let mut buf = String::new();
     loop {
-        try_read(output, &mut out, &mut buf);
+        try_read(session, &mut out, &mut buf);
         if buf.contains(s) {
             return buf;
         }
@@ -49,8 +47,8 @@ fn wait_for(output: &mut PipeReader, s: &str) -> String {
     }
 }
 
-fn try_read(o: &mut PipeReader, out: &mut Vec<u8>, buf: &mut String) {
-    match o.read(out) {
+fn try_read(session: &mut PtySession, out: &mut [u8], buf: &mut String) {
+    match session.read(out) {
         Ok(n) => {
             let s = String::from_utf8_lossy(&out[..n]);
             buf.push_str(&s);
@@ -5,7 +5,8 @@ fn main() {
 
     assert_eq!(true, console.is_stdin_empty().unwrap());
 
-    console.set_raw().unwrap();
+    let _raw = console.enter_raw().unwrap();
+    console.set_title("conpty console example").unwrap();
 
     println!("Type `]` character to exit");
 
@@ -25,6 +26,4 @@ fn main() {
             break;
         }
     }
-
-    console.reset().unwrap();
 }
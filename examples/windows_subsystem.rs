@@ -3,13 +3,18 @@
 use std::{
     fmt::Display,
     io::prelude::*,
+    process::Command,
     time::{Duration, Instant},
 };
 
-use conpty::io::PipeReader;
+use conpty::{io::PipeReader, ProcessOptions};
 
 fn main() {
-    let mut p = conpty::spawn("cmd").unwrap();
+    // there's no parent console so we must not touch it
+    let mut p = ProcessOptions::default()
+        .headless(80, 25)
+        .spawn(Command::new("cmd"))
+        .unwrap();
 
     to_file(format!("Process PID={:?}", p.pid()));
 
@@ -58,7 +63,7 @@ fn wait_for(output: &mut PipeReader, s: &str) -> String {
     }
 }
 
-fn try_read(o: &mut PipeReader, out: &mut Vec<u8>, buf: &mut String) {
+fn try_read(o: &mut PipeReader, out: &mut [u8], buf: &mut String) {
     match o.read(out) {
         Ok(n) => {
             let s = String::from_utf8_lossy(&out[..n]);
@@ -0,0 +1,166 @@
+//! Module contains a set of `PROC_THREAD_ATTRIBUTE`s which are applied to a spawned process.
+
+use std::{ffi::c_void, mem::size_of, ptr::null_mut};
+
+use windows::{
+    core::{self as win, HRESULT},
+    Win32::{
+        Foundation::HANDLE,
+        System::{
+            Console::HPCON,
+            Threading::{
+                DeleteProcThreadAttributeList, InitializeProcThreadAttributeList,
+                UpdateProcThreadAttribute, LPPROC_THREAD_ATTRIBUTE_LIST,
+            },
+        },
+    },
+};
+
+// const PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE: usize = 22 | 0x0002_0000;
+const PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE: usize = 0x00020016;
+// const PROC_THREAD_ATTRIBUTE_HANDLE_LIST: usize = 2 | 0x0002_0000;
+const PROC_THREAD_ATTRIBUTE_HANDLE_LIST: usize = 0x00020002;
+
+/// AttributeSet collects attributes which are passed to `CreateProcessW`.
+///
+/// The values are owned by the set as `UpdateProcThreadAttribute` keeps only pointers to them.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct AttributeSet {
+    attributes: Vec<(usize, AttributeValue)>,
+}
+
+#[derive(Debug, Clone)]
+enum AttributeValue {
+    // a value which is passed as the pointer itself (e.g. HPCON)
+    Pointer(isize, usize),
+    // a value which is passed as a pointer to an array of handles
+    Handles(Vec<HANDLE>),
+}
+
+impl AttributeSet {
+    /// Attaches a process to a pseudo console.
+    pub(crate) fn pseudo_console(&mut self, console: HPCON) -> &mut Self {
+        let value = AttributeValue::Pointer(console.0, size_of::<HPCON>());
+        self.set(PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE, value)
+    }
+
+    /// Restricts handles which are inherited by a process to the given ones.
+    pub(crate) fn handle_list(&mut self, handles: Vec<HANDLE>) -> &mut Self {
+        self.set(
+            PROC_THREAD_ATTRIBUTE_HANDLE_LIST,
+            AttributeValue::Handles(handles),
+        )
+    }
+
+    fn set(&mut self, attribute: usize, value: AttributeValue) -> &mut Self {
+        match self.attributes.iter_mut().find(|(id, _)| *id == attribute) {
+            Some((_, v)) => *v = value,
+            None => self.attributes.push((attribute, value)),
+        }
+
+        self
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.attributes.len()
+    }
+}
+
+/// AttributeList is an initialized `PROC_THREAD_ATTRIBUTE_LIST`.
+///
+/// It's deleted on drop.
+#[derive(Debug)]
+pub(crate) struct AttributeList {
+    // usize is used to have the list aligned
+    buf: Vec<usize>,
+    // the values are referenced by the list
+    _attributes: AttributeSet,
+}
+
+impl AttributeList {
+    pub(crate) fn new(attributes: AttributeSet) -> win::Result<Self> {
+        let count = attributes.len() as u32;
+
+        let mut size: usize = 0;
+        let res = unsafe {
+            InitializeProcThreadAttributeList(
+                LPPROC_THREAD_ATTRIBUTE_LIST(null_mut()),
+                count,
+                0,
+                &mut size,
+            )
+        };
+        if res.is_ok() /* according to the documentation this initial call must fail! */ || size == 0
+        {
+            // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-initializeprocthreadattributelist#return-value
+            return Err(win::Error::new(
+                HRESULT::default(),
+                "failed initialize proc attribute list",
+            ));
+        }
+
+        // the list is wrapped only once it's initialized,
+        // so it's never deleted without an initialization
+        let mut buf: Vec<usize> = vec![0; size.div_ceil(size_of::<usize>())];
+        let ptr = LPPROC_THREAD_ATTRIBUTE_LIST(buf.as_mut_ptr() as _);
+        unsafe { InitializeProcThreadAttributeList(ptr, count, 0, &mut size)? };
+
+        // moving the Vec doesn't move its data, so `ptr` stays valid
+        let list = Self {
+            buf,
+            _attributes: attributes,
+        };
+
+        for (attribute, value) in &list._attributes.attributes {
+            let (value, size) = match value {
+                AttributeValue::Pointer(value, size) => (*value as *const c_void, *size),
+                AttributeValue::Handles(handles) => (
+                    handles.as_ptr() as *const c_void,
+                    handles.len() * size_of::<HANDLE>(),
+                ),
+            };
+
+            // the list is deleted on drop in case of an error
+            unsafe {
+                UpdateProcThreadAttribute(ptr, 0, *attribute, Some(value), size, None, None)?
+            };
+        }
+
+        Ok(list)
+    }
+
+    pub(crate) fn as_ptr(&mut self) -> LPPROC_THREAD_ATTRIBUTE_LIST {
+        LPPROC_THREAD_ATTRIBUTE_LIST(self.buf.as_mut_ptr() as _)
+    }
+}
+
+impl Drop for AttributeList {
+    fn drop(&mut self) {
+        unsafe { DeleteProcThreadAttributeList(self.as_ptr()) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attribute_set_test() {
+        let mut attributes = AttributeSet::default();
+        assert_eq!(attributes.len(), 0);
+
+        let _ = attributes.pseudo_console(HPCON(1));
+        let _ = attributes.pseudo_console(HPCON(2));
+        assert_eq!(attributes.len(), 1);
+        assert!(matches!(
+            attributes.attributes[0],
+            (
+                PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE,
+                AttributeValue::Pointer(2, _)
+            )
+        ));
+
+        let _ = attributes.handle_list(vec![HANDLE(3)]);
+        assert_eq!(attributes.len(), 2);
+    }
+}
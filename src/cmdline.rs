@@ -0,0 +1,116 @@
+//! Module contains a quoting of a command line which is parsed by `CommandLineToArgvW` (MSVC rules).
+
+use std::{
+    ffi::{OsStr, OsString},
+    os::windows::ffi::{OsStrExt, OsStringExt},
+};
+
+const QUOTE: u16 = b'"' as u16;
+const BACKSLASH: u16 = b'\\' as u16;
+
+/// Builds a command line from a program and its arguments,
+/// so the process gets exactly the same `argv`.
+pub(crate) fn build_argv_commandline(program: &OsStr, args: &[OsString]) -> OsString {
+    let mut buf = quote_program(program);
+    for arg in args {
+        buf.push(" ");
+        buf.push(quote_arg(arg));
+    }
+
+    buf
+}
+
+/// Quotes a program name.
+///
+/// A program name is parsed differently from arguments,
+/// it ends with a next quote and backslashes are not special,
+/// so it's only wrapped in quotes if needed.
+pub(crate) fn quote_program(program: &OsStr) -> OsString {
+    if !needs_quotes(program) {
+        return program.to_owned();
+    }
+
+    let mut buf = OsString::from("\"");
+    buf.push(program);
+    buf.push("\"");
+    buf
+}
+
+/// Quotes an argument.
+///
+/// An argument is wrapped in quotes if it's empty or has a whitespace.
+/// Quotes are escaped with a backslash,
+/// as well as backslashes which precede a quote (including the closing one).
+pub(crate) fn quote_arg(arg: &OsStr) -> OsString {
+    let quote = needs_quotes(arg);
+
+    let mut buf = Vec::new();
+    if quote {
+        buf.push(QUOTE);
+    }
+
+    let mut backslashes = 0;
+    for c in arg.encode_wide() {
+        match c {
+            BACKSLASH => backslashes += 1,
+            QUOTE => {
+                // each preceding backslash and the quote itself are escaped
+                buf.resize(buf.len() + backslashes + 1, BACKSLASH);
+                backslashes = 0;
+            }
+            _ => backslashes = 0,
+        }
+
+        buf.push(c);
+    }
+
+    if quote {
+        // backslashes before the closing quote are escaped
+        buf.resize(buf.len() + backslashes, BACKSLASH);
+        buf.push(QUOTE);
+    }
+
+    OsString::from_wide(&buf)
+}
+
+fn needs_quotes(arg: &OsStr) -> bool {
+    arg.is_empty()
+        || arg
+            .encode_wide()
+            .any(|c| c == ' ' as u16 || c == '\t' as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_arg_test() {
+        let tests = [
+            ("abc", "abc"),
+            ("", r#""""#),
+            ("hello world", r#""hello world""#),
+            (r#"a"b"#, r#"a\"b"#),
+            (r"a\b", r"a\b"),
+            (r"a\ ", r#""a\ ""#),
+            (r"a b\", r#""a b\\""#),
+            (r#"a\"b"#, r#"a\\\"b"#),
+            (r#"a "b" c"#, r#""a \"b\" c""#),
+        ];
+
+        for (arg, expected) in tests {
+            assert_eq!(quote_arg(OsStr::new(arg)), expected, "{:?}", arg);
+        }
+    }
+
+    #[test]
+    fn build_argv_commandline_test() {
+        let args = [OsString::from("/C"), OsString::from("echo a b")];
+        assert_eq!(
+            build_argv_commandline(OsStr::new(r"C:\Program Files\app.exe"), &args),
+            r#""C:\Program Files\app.exe" /C "echo a b""#
+        );
+
+        assert_eq!(build_argv_commandline(OsStr::new("cmd"), &[]), "cmd");
+    }
+}
@@ -1,12 +1,15 @@
 //! Module contains a handy functions for terminal.
 
-use windows::core::Result as WinResult;
+use std::iter;
+
+use windows::core::{Result as WinResult, PCWSTR};
 use windows::Win32::Foundation::WAIT_OBJECT_0;
 use windows::Win32::{
-    Foundation::HANDLE,
+    Foundation::{GetLastError, SetLastError, ERROR_SUCCESS, HANDLE},
     System::{
         Console::{
-            GetConsoleMode, GetStdHandle, SetConsoleMode, CONSOLE_MODE,
+            GetConsoleMode, GetConsoleTitleW, GetStdHandle, SetConsoleMode, SetConsoleTitleW,
+            COMMON_LVB_REVERSE_VIDEO, COMMON_LVB_UNDERSCORE, CONSOLE_MODE,
             DISABLE_NEWLINE_AUTO_RETURN, ENABLE_ECHO_INPUT, ENABLE_EXTENDED_FLAGS,
             ENABLE_INSERT_MODE, ENABLE_LINE_INPUT, ENABLE_MOUSE_INPUT, ENABLE_PROCESSED_INPUT,
             ENABLE_QUICK_EDIT_MODE, ENABLE_VIRTUAL_TERMINAL_INPUT, STD_ERROR_HANDLE,
@@ -16,7 +19,13 @@ use windows::Win32::{
     },
 };
 
-use crate::error::Error;
+use crate::{
+    error::Error,
+    util::{console_attached, screen_buffer_info, window_size},
+};
+
+// A maximum length of a title we save.
+const MAX_TITLE_LEN: usize = 4096;
 
 /// Console represents a terminal session with opened stdin, stdout and stderr.
 #[derive(Debug, Clone)]
@@ -27,11 +36,19 @@ pub struct Console {
     stdin_mode: CONSOLE_MODE,
     stdout_mode: CONSOLE_MODE,
     stderr_mode: CONSOLE_MODE,
+    // a NULL terminated title, it's None if it couldn't be retrieved
+    title: Option<Vec<u16>>,
 }
 
 impl Console {
     /// Creates a console from default stdin, stdout and stderr.
+    ///
+    /// [Error::NoConsoleAttached] is returned if the current process has no console.
     pub fn current() -> Result<Self, Error> {
+        if !console_attached() {
+            return Err(Error::NoConsoleAttached);
+        }
+
         // We don't close these handle on drop because:
         //  It is not required to CloseHandle when done with the handle retrieved from GetStdHandle.
         //  The returned value is simply a copy of the value stored in the process table.
@@ -43,6 +60,8 @@ impl Console {
         let stdout_mode = get_console_mode(stdout)?;
         let stderr_mode = get_console_mode(stderr)?;
 
+        let title = get_console_title();
+
         Ok(Self {
             stderr,
             stderr_mode,
@@ -50,13 +69,30 @@ impl Console {
             stdin_mode,
             stdout,
             stdout_mode,
+            title,
         })
     }
 
     /// Sets terminal in a raw mode.
     /// Raw mode is a mode where most of consoles processing is ommited.
     pub fn set_raw(&self) -> Result<(), Error> {
-        set_raw_stdin(self.stdin, self.stdin_mode)?;
+        self.set_raw_with(&RawModeOptions::default())
+    }
+
+    /// Sets terminal in a raw mode with given options.
+    ///
+    /// See [RawModeOptions].
+    ///
+    /// ```ignore
+    /// use conpty::console::{Console, RawModeOptions};
+    ///
+    /// let console = Console::current().unwrap();
+    /// console.set_raw_with(RawModeOptions::default().mouse(true)).unwrap();
+    /// ```
+    pub fn set_raw_with(&self, opts: &RawModeOptions) -> Result<(), Error> {
+        unsafe {
+            SetConsoleMode(self.stdin, raw_stdin_mode(self.stdin_mode, opts))?;
+        }
 
         unsafe {
             SetConsoleMode(self.stdout, self.stdout_mode | DISABLE_NEWLINE_AUTO_RETURN)?;
@@ -68,15 +104,71 @@ impl Console {
         Ok(())
     }
 
-    /// Sets terminal in a mode which was initially used on handles.
+    /// Sets terminal in a raw mode until the returned guard is dropped.
+    ///
+    /// The guard calls [Self::reset] on drop,
+    /// so the terminal is restored even if the code panics or returns early with an error.
+    ///
+    /// ```ignore
+    /// let console = conpty::console::Console::current().unwrap();
+    /// let _guard = console.enter_raw().unwrap();
+    /// // the terminal is in a raw mode here
+    /// ```
+    pub fn enter_raw(&self) -> Result<RawModeGuard<'_>, Error> {
+        self.enter_raw_with(&RawModeOptions::default())
+    }
+
+    /// Sets terminal in a raw mode with given options until the returned guard is dropped.
+    ///
+    /// See [Self::enter_raw] and [RawModeOptions].
+    pub fn enter_raw_with(&self, opts: &RawModeOptions) -> Result<RawModeGuard<'_>, Error> {
+        self.set_raw_with(opts)?;
+        Ok(RawModeGuard { console: self })
+    }
+
+    /// Sets terminal in a mode which was initially used on handles
+    /// and restores the title.
     pub fn reset(&self) -> Result<(), Error> {
         for (handle, mode) in self.streams() {
             unsafe { SetConsoleMode(handle, mode)? };
         }
 
+        if let Some(title) = &self.title {
+            unsafe { SetConsoleTitleW(PCWSTR(title.as_ptr()))? };
+        }
+
+        Ok(())
+    }
+
+    /// Sets a title of the console window.
+    ///
+    /// The original title is restored by [Self::reset].
+    pub fn set_title(&self, title: &str) -> Result<(), Error> {
+        let title = title
+            .encode_utf16()
+            .chain(iter::once(0))
+            .collect::<Vec<_>>();
+        unsafe { SetConsoleTitleW(PCWSTR(title.as_ptr()))? };
+
         Ok(())
     }
 
+    /// Enables or disables Quick Edit mode of stdin, returning whether it was enabled.
+    ///
+    /// Quick Edit mode lets a user select text with a mouse,
+    /// so mouse events don't reach an application while it's on.
+    /// The rest of the mode flags are kept as is.
+    pub fn set_quick_edit(&self, on: bool) -> Result<bool, Error> {
+        let mode = get_console_mode(self.stdin)?;
+        let enabled = mode.contains(ENABLE_QUICK_EDIT_MODE);
+
+        unsafe {
+            SetConsoleMode(self.stdin, quick_edit_mode(mode, on))?;
+        }
+
+        Ok(enabled)
+    }
+
     /// Verifies if there's something in stdin to read.
     ///
     /// It can be used to determine if the call to `[std::io::stdin].read()` will block
@@ -86,6 +178,23 @@ impl Console {
         Ok(empty)
     }
 
+    /// Returns current text attributes (colors) of stdout.
+    pub fn text_attributes(&self) -> Result<Attributes, Error> {
+        let info = screen_buffer_info(self.stdout)?;
+        Ok(Attributes::from_raw(info.wAttributes.0))
+    }
+
+    /// Returns a size of the console window as (columns, rows).
+    ///
+    /// It can be used to spawn a [crate::Process] of the same size,
+    /// see [crate::ProcessOptions::inherit_from].
+    pub fn window_size(&self) -> Result<(u16, u16), Error> {
+        let info = screen_buffer_info(self.stdout)?;
+        let (columns, rows) = window_size(&info);
+
+        Ok((columns as u16, rows as u16))
+    }
+
     fn streams(&self) -> [(HANDLE, CONSOLE_MODE); 3] {
         [
             (self.stdin, self.stdin_mode),
@@ -95,6 +204,167 @@ impl Console {
     }
 }
 
+/// Options of a raw mode of stdin.
+///
+/// The default options are the ones used by [Console::set_raw].
+#[derive(Debug, Clone)]
+pub struct RawModeOptions {
+    mouse: bool,
+    quick_edit: bool,
+    vt_input: bool,
+}
+
+impl RawModeOptions {
+    /// Makes mouse events available in stdin (`ENABLE_MOUSE_INPUT`).
+    ///
+    /// Enabling mouse also disables Quick Edit mode, as it steals mouse events,
+    /// regardless of [Self::quick_edit].
+    ///
+    /// It's off by default.
+    pub fn mouse(&mut self, on: bool) -> &mut Self {
+        self.mouse = on;
+        self
+    }
+
+    /// Lets a user select text with a mouse (`ENABLE_QUICK_EDIT_MODE`).
+    ///
+    /// It's on by default.
+    pub fn quick_edit(&mut self, on: bool) -> &mut Self {
+        self.quick_edit = on;
+        self
+    }
+
+    /// Makes key presses be reported as VT sequences (`ENABLE_VIRTUAL_TERMINAL_INPUT`).
+    ///
+    /// It's on by default.
+    pub fn vt_input(&mut self, on: bool) -> &mut Self {
+        self.vt_input = on;
+        self
+    }
+}
+
+impl Default for RawModeOptions {
+    fn default() -> Self {
+        Self {
+            mouse: false,
+            quick_edit: true,
+            vt_input: true,
+        }
+    }
+}
+
+/// RawModeGuard resets a [Console] on drop.
+///
+/// It's created by [Console::enter_raw].
+#[must_use = "the console is reset once the guard is dropped"]
+#[derive(Debug)]
+pub struct RawModeGuard<'a> {
+    console: &'a Console,
+}
+
+impl Drop for RawModeGuard<'_> {
+    fn drop(&mut self) {
+        // there's nothing we can do about an error in drop
+        let _ = self.console.reset();
+    }
+}
+
+/// Attributes represents text attributes of a console (`wAttributes`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Attributes {
+    /// A foreground color.
+    pub foreground: Color,
+    /// A background color.
+    pub background: Color,
+    /// Text is underscored.
+    pub underscore: bool,
+    /// Foreground and background colors are swapped.
+    pub reverse_video: bool,
+    /// An original value of the attributes.
+    pub raw: u16,
+}
+
+impl Attributes {
+    /// Decodes attributes from a raw value.
+    pub fn from_raw(raw: u16) -> Self {
+        Self {
+            foreground: Color::from_bits(raw),
+            background: Color::from_bits(raw >> 4),
+            underscore: raw & COMMON_LVB_UNDERSCORE.0 != 0,
+            reverse_video: raw & COMMON_LVB_REVERSE_VIDEO.0 != 0,
+            raw,
+        }
+    }
+}
+
+/// Color is a one of 16 console colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    /// Black.
+    Black,
+    /// Blue.
+    Blue,
+    /// Green.
+    Green,
+    /// Cyan.
+    Cyan,
+    /// Red.
+    Red,
+    /// Magenta.
+    Magenta,
+    /// Yellow.
+    Yellow,
+    /// White (Gray).
+    White,
+    /// Bright black (Dark gray).
+    BrightBlack,
+    /// Bright blue.
+    BrightBlue,
+    /// Bright green.
+    BrightGreen,
+    /// Bright cyan.
+    BrightCyan,
+    /// Bright red.
+    BrightRed,
+    /// Bright magenta.
+    BrightMagenta,
+    /// Bright yellow.
+    BrightYellow,
+    /// Bright white.
+    BrightWhite,
+}
+
+impl Color {
+    /// Verifies if it's an intense (bright) color.
+    pub fn is_bright(&self) -> bool {
+        (*self as u8) & 0x8 != 0
+    }
+
+    // uses 4 low bits: blue, green, red and intensity
+    fn from_bits(bits: u16) -> Self {
+        const COLORS: [Color; 16] = [
+            Color::Black,
+            Color::Blue,
+            Color::Green,
+            Color::Cyan,
+            Color::Red,
+            Color::Magenta,
+            Color::Yellow,
+            Color::White,
+            Color::BrightBlack,
+            Color::BrightBlue,
+            Color::BrightGreen,
+            Color::BrightCyan,
+            Color::BrightRed,
+            Color::BrightMagenta,
+            Color::BrightYellow,
+            Color::BrightWhite,
+        ];
+
+        COLORS[(bits & 0xF) as usize]
+    }
+}
+
 fn get_console_mode(h: HANDLE) -> WinResult<CONSOLE_MODE> {
     let mut mode = CONSOLE_MODE::default();
     unsafe {
@@ -103,25 +373,115 @@ fn get_console_mode(h: HANDLE) -> WinResult<CONSOLE_MODE> {
     Ok(mode)
 }
 
-fn set_raw_stdin(stdin: HANDLE, mut mode: CONSOLE_MODE) -> WinResult<()> {
+fn get_console_title() -> Option<Vec<u16>> {
+    let mut title = vec![0; MAX_TITLE_LEN];
+    // 0 is returned both for an empty title and on error,
+    // so the last error is cleared to tell them apart
+    unsafe { SetLastError(ERROR_SUCCESS) };
+    let len = unsafe { GetConsoleTitleW(&mut title) } as usize;
+    if len == 0 && unsafe { GetLastError() } != ERROR_SUCCESS {
+        return None;
+    }
+
+    title.truncate(len.min(MAX_TITLE_LEN - 1));
+    title.push(0);
+
+    Some(title)
+}
+
+fn quick_edit_mode(mut mode: CONSOLE_MODE, on: bool) -> CONSOLE_MODE {
+    // ENABLE_QUICK_EDIT_MODE is taken into account only with ENABLE_EXTENDED_FLAGS
+    mode |= ENABLE_EXTENDED_FLAGS;
+
+    match on {
+        true => mode | ENABLE_QUICK_EDIT_MODE,
+        false => mode & !ENABLE_QUICK_EDIT_MODE,
+    }
+}
+
+fn raw_stdin_mode(mut mode: CONSOLE_MODE, opts: &RawModeOptions) -> CONSOLE_MODE {
     mode &= !ENABLE_ECHO_INPUT;
     mode &= !ENABLE_LINE_INPUT;
-    mode &= !ENABLE_MOUSE_INPUT;
-    mode &= !ENABLE_LINE_INPUT;
     mode &= !ENABLE_PROCESSED_INPUT;
 
     mode |= ENABLE_EXTENDED_FLAGS;
     mode |= ENABLE_INSERT_MODE;
-    mode |= ENABLE_QUICK_EDIT_MODE;
 
-    let vt_input_supported = true;
-    if vt_input_supported {
-        mode |= ENABLE_VIRTUAL_TERMINAL_INPUT;
+    match opts.mouse {
+        true => mode |= ENABLE_MOUSE_INPUT,
+        false => mode &= !ENABLE_MOUSE_INPUT,
     }
 
-    unsafe {
-        SetConsoleMode(stdin, mode)?;
+    // quick edit mode steals mouse events
+    mode = quick_edit_mode(mode, opts.quick_edit && !opts.mouse);
+
+    match opts.vt_input {
+        true => mode |= ENABLE_VIRTUAL_TERMINAL_INPUT,
+        false => mode &= !ENABLE_VIRTUAL_TERMINAL_INPUT,
     }
 
-    Ok(())
+    mode
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use windows::Win32::System::Console::{
+        BACKGROUND_BLUE, FOREGROUND_GREEN, FOREGROUND_INTENSITY, FOREGROUND_RED,
+    };
+
+    #[test]
+    fn attributes_test() {
+        let raw = FOREGROUND_RED | FOREGROUND_GREEN | FOREGROUND_INTENSITY | BACKGROUND_BLUE;
+        let attrs = Attributes::from_raw(raw.0);
+        assert_eq!(attrs.foreground, Color::BrightYellow);
+        assert_eq!(attrs.background, Color::Blue);
+        assert!(attrs.foreground.is_bright());
+        assert!(!attrs.underscore);
+        assert!(!attrs.reverse_video);
+
+        let attrs = Attributes::from_raw(0x07 | COMMON_LVB_UNDERSCORE.0);
+        assert_eq!(attrs.foreground, Color::White);
+        assert_eq!(attrs.background, Color::Black);
+        assert!(attrs.underscore);
+    }
+
+    #[test]
+    fn raw_stdin_mode_test() {
+        let mode = ENABLE_ECHO_INPUT | ENABLE_LINE_INPUT | ENABLE_PROCESSED_INPUT;
+
+        let raw = raw_stdin_mode(mode, &RawModeOptions::default());
+        assert_eq!(
+            raw,
+            ENABLE_EXTENDED_FLAGS
+                | ENABLE_INSERT_MODE
+                | ENABLE_QUICK_EDIT_MODE
+                | ENABLE_VIRTUAL_TERMINAL_INPUT
+        );
+
+        let raw = raw_stdin_mode(mode, RawModeOptions::default().mouse(true));
+        assert_eq!(
+            raw,
+            ENABLE_EXTENDED_FLAGS
+                | ENABLE_INSERT_MODE
+                | ENABLE_MOUSE_INPUT
+                | ENABLE_VIRTUAL_TERMINAL_INPUT
+        );
+    }
+
+    #[test]
+    fn quick_edit_mode_test() {
+        let mode = ENABLE_ECHO_INPUT | ENABLE_QUICK_EDIT_MODE;
+        assert_eq!(
+            quick_edit_mode(mode, false),
+            ENABLE_ECHO_INPUT | ENABLE_EXTENDED_FLAGS
+        );
+
+        let mode = ENABLE_LINE_INPUT;
+        assert_eq!(
+            quick_edit_mode(mode, true),
+            ENABLE_LINE_INPUT | ENABLE_EXTENDED_FLAGS | ENABLE_QUICK_EDIT_MODE
+        );
+    }
 }
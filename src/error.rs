@@ -1,6 +1,6 @@
 //! Module contains a library error.
 
-use std::{fmt, time::Duration};
+use std::{ffi::OsString, fmt, path::PathBuf, time::Duration};
 
 use windows::{core as win, Win32::Foundation::WAIT_EVENT};
 
@@ -15,9 +15,109 @@ pub enum Error {
     WaitFailed(WAIT_EVENT),
     /// Input already closed
     InputClosed,
+    /// Pseudo console is already closed.
+    ConsoleClosed,
+    /// A program which was requested to be spawned was not found.
+    ProgramNotFound(OsString),
+    /// A working directory of a process doesn't exist.
+    CurrentDirNotFound(PathBuf),
+    /// A console size is invalid, both columns and rows must be positive.
+    InvalidSize(i16, i16),
+    /// A program which was requested to be spawned can't be accessed.
+    PermissionDenied(OsString),
+    /// Process creation flags are incompatible with a pseudo console.
+    InvalidCreationFlags(u32),
+    /// The current process has no console attached (e.g. it's a `windows_subsystem = "windows"` application).
+    NoConsoleAttached,
+    /// The console screen is not tracked, see [crate::ProcessOptions::track_screen].
+    ScreenNotTracked,
 }
 
-impl std::error::Error for Error {}
+impl Error {
+    /// Returns a stable numeric code of the error, which can be passed over FFI.
+    ///
+    /// - [Error::Win] returns its `HRESULT` as is, so it's always in `i32` range.
+    /// - Crate errors are negative numbers below `i32::MIN` so they never clash with `HRESULT`s.
+    ///
+    /// | Variant                       | Code             |
+    /// |-------------------------------|------------------|
+    /// | [Error::Win]                  | `HRESULT`        |
+    /// | [Error::Timeout]              | `-0x1_0000_0001` |
+    /// | [Error::WaitFailed]           | `-0x1_0000_0002` |
+    /// | [Error::InputClosed]          | `-0x1_0000_0003` |
+    /// | [Error::ConsoleClosed]        | `-0x1_0000_0004` |
+    /// | [Error::ProgramNotFound]      | `-0x1_0000_0005` |
+    /// | [Error::CurrentDirNotFound]   | `-0x1_0000_0006` |
+    /// | [Error::InvalidSize]          | `-0x1_0000_0007` |
+    /// | [Error::PermissionDenied]     | `-0x1_0000_0008` |
+    /// | [Error::InvalidCreationFlags] | `-0x1_0000_0009` |
+    /// | [Error::NoConsoleAttached]    | `-0x1_0000_000A` |
+    /// | [Error::ScreenNotTracked]     | `-0x1_0000_000B` |
+    ///
+    /// The codes are kept the same across versions.
+    pub fn code(&self) -> i64 {
+        match self {
+            Self::Win(err) => err.code().0 as i64,
+            Self::Timeout(_) => CRATE_CODE_BASE - 1,
+            Self::WaitFailed(_) => CRATE_CODE_BASE - 2,
+            Self::InputClosed => CRATE_CODE_BASE - 3,
+            Self::ConsoleClosed => CRATE_CODE_BASE - 4,
+            Self::ProgramNotFound(_) => CRATE_CODE_BASE - 5,
+            Self::CurrentDirNotFound(_) => CRATE_CODE_BASE - 6,
+            Self::InvalidSize(..) => CRATE_CODE_BASE - 7,
+            Self::PermissionDenied(_) => CRATE_CODE_BASE - 8,
+            Self::InvalidCreationFlags(_) => CRATE_CODE_BASE - 9,
+            Self::NoConsoleAttached => CRATE_CODE_BASE - 10,
+            Self::ScreenNotTracked => CRATE_CODE_BASE - 11,
+        }
+    }
+
+    /// Returns an HRESULT of a [Error::Win] error and `None` for other errors.
+    ///
+    /// A Win32 error code is wrapped into an HRESULT,
+    /// so it can be compared with e.g. `ERROR_BROKEN_PIPE.to_hresult().0`.
+    pub fn win_code(&self) -> Option<i32> {
+        match self {
+            Self::Win(err) => Some(err.code().0),
+            _ => None,
+        }
+    }
+}
+
+const CRATE_CODE_BASE: i64 = -0x1_0000_0000;
+
+// Windows errors are equal if their codes are equal,
+// a message and other info which may be attached to them is not compared.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Win(a), Self::Win(b)) => a.code() == b.code(),
+            (Self::Timeout(a), Self::Timeout(b)) => a == b,
+            (Self::WaitFailed(a), Self::WaitFailed(b)) => a == b,
+            (Self::InputClosed, Self::InputClosed) => true,
+            (Self::ConsoleClosed, Self::ConsoleClosed) => true,
+            (Self::ProgramNotFound(a), Self::ProgramNotFound(b)) => a == b,
+            (Self::CurrentDirNotFound(a), Self::CurrentDirNotFound(b)) => a == b,
+            (Self::InvalidSize(x1, y1), Self::InvalidSize(x2, y2)) => x1 == x2 && y1 == y2,
+            (Self::PermissionDenied(a), Self::PermissionDenied(b)) => a == b,
+            (Self::InvalidCreationFlags(a), Self::InvalidCreationFlags(b)) => a == b,
+            (Self::NoConsoleAttached, Self::NoConsoleAttached) => true,
+            (Self::ScreenNotTracked, Self::ScreenNotTracked) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Error {}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Win(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -26,6 +126,29 @@ impl fmt::Display for Error {
             Self::Timeout(limit) => writeln!(f, "A timeout {:?} was reached", limit),
             Self::WaitFailed(event_id) => writeln!(f, "Waiting failed. WAIT_EVENT: {:?}", event_id),
             Self::InputClosed => writeln!(f, "The input is already closed"),
+            Self::ConsoleClosed => writeln!(f, "The pseudo console is already closed"),
+            Self::ProgramNotFound(program) => writeln!(f, "Program {:?} was not found", program),
+            Self::CurrentDirNotFound(dir) => {
+                writeln!(f, "Working directory {:?} doesn't exist", dir)
+            }
+            Self::InvalidSize(x, y) => writeln!(
+                f,
+                "Console size {}x{} is invalid, columns and rows must be positive",
+                x, y
+            ),
+            Self::PermissionDenied(program) => {
+                writeln!(f, "Permission to run program {:?} was denied", program)
+            }
+            Self::InvalidCreationFlags(flags) => writeln!(
+                f,
+                "Creation flags {:#x} are incompatible with a pseudo console",
+                flags
+            ),
+            Self::NoConsoleAttached => writeln!(
+                f,
+                "The current process has no console attached (is it a GUI subsystem application?)"
+            ),
+            Self::ScreenNotTracked => writeln!(f, "The console screen is not tracked"),
         }
     }
 }
@@ -50,6 +173,41 @@ impl From<Error> for std::io::Error {
                 ErrorKind::NotFound,
                 String::from("Input to console was already closed"),
             ),
+            Error::ConsoleClosed => IoError::new(
+                ErrorKind::NotConnected,
+                String::from("Pseudo console was already closed"),
+            ),
+            Error::ProgramNotFound(program) => IoError::new(
+                ErrorKind::NotFound,
+                format!("Program {:?} was not found", program),
+            ),
+            Error::CurrentDirNotFound(dir) => IoError::new(
+                ErrorKind::NotFound,
+                format!("Working directory {:?} doesn't exist", dir),
+            ),
+            Error::InvalidSize(x, y) => IoError::new(
+                ErrorKind::InvalidInput,
+                format!("Console size {}x{} is invalid", x, y),
+            ),
+            Error::PermissionDenied(program) => IoError::new(
+                ErrorKind::PermissionDenied,
+                format!("Permission to run program {:?} was denied", program),
+            ),
+            Error::InvalidCreationFlags(flags) => IoError::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Creation flags {:#x} are incompatible with a pseudo console",
+                    flags
+                ),
+            ),
+            Error::NoConsoleAttached => IoError::new(
+                ErrorKind::NotConnected,
+                String::from("The current process has no console attached"),
+            ),
+            Error::ScreenNotTracked => IoError::new(
+                ErrorKind::Unsupported,
+                String::from("The console screen is not tracked"),
+            ),
             Error::WaitFailed(wait_event) => IoError::new(
                 ErrorKind::Interrupted,
                 format!("Waiting for process failed. WAIT_EVENT: {:?}", wait_event),
@@ -57,3 +215,84 @@ impl From<Error> for std::io::Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use windows::Win32::Foundation::{ERROR_BROKEN_PIPE, E_FAIL, E_INVALIDARG, WAIT_FAILED};
+
+    #[test]
+    fn error_code_test() {
+        let tests = [
+            (
+                Error::Win(win::Error::from(E_INVALIDARG)),
+                E_INVALIDARG.0 as i64,
+            ),
+            (Error::Timeout(Duration::from_secs(1)), -0x1_0000_0001),
+            (Error::WaitFailed(WAIT_FAILED), -0x1_0000_0002),
+            (Error::InputClosed, -0x1_0000_0003),
+            (Error::ConsoleClosed, -0x1_0000_0004),
+            (Error::ProgramNotFound(OsString::new()), -0x1_0000_0005),
+            (Error::CurrentDirNotFound(PathBuf::new()), -0x1_0000_0006),
+            (Error::InvalidSize(0, 0), -0x1_0000_0007),
+            (Error::PermissionDenied(OsString::new()), -0x1_0000_0008),
+            (Error::InvalidCreationFlags(0), -0x1_0000_0009),
+            (Error::NoConsoleAttached, -0x1_0000_000A),
+            (Error::ScreenNotTracked, -0x1_0000_000B),
+        ];
+
+        for (err, expected) in tests {
+            assert_eq!(err.code(), expected, "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn win_code_test() {
+        let err = Error::Win(win::Error::from(ERROR_BROKEN_PIPE.to_hresult()));
+        assert_eq!(err.win_code(), Some(ERROR_BROKEN_PIPE.to_hresult().0));
+        assert_eq!(err.win_code(), Some(-2147024787));
+
+        assert_eq!(Error::InputClosed.win_code(), None);
+    }
+
+    #[test]
+    fn error_source_test() {
+        use std::error::Error as _;
+
+        let err = Error::Win(win::Error::from(E_INVALIDARG));
+        let source = err.source().unwrap();
+        let source = source.downcast_ref::<win::Error>().unwrap();
+        assert_eq!(source.code(), E_INVALIDARG);
+
+        assert!(Error::InputClosed.source().is_none());
+    }
+
+    #[test]
+    fn error_eq_test() {
+        assert_eq!(
+            Error::Win(win::Error::from(E_INVALIDARG)),
+            Error::Win(win::Error::new(E_INVALIDARG, "a message"))
+        );
+        assert_ne!(
+            Error::Win(win::Error::from(E_INVALIDARG)),
+            Error::Win(win::Error::from(E_FAIL))
+        );
+        assert_eq!(
+            Error::WaitFailed(WAIT_FAILED),
+            Error::WaitFailed(WAIT_FAILED)
+        );
+        assert_eq!(
+            Error::Timeout(Duration::from_secs(1)),
+            Error::Timeout(Duration::from_secs(1))
+        );
+        assert_ne!(
+            Error::Timeout(Duration::from_secs(1)),
+            Error::Timeout(Duration::from_secs(2))
+        );
+        assert_eq!(Error::InputClosed, Error::InputClosed);
+        assert_ne!(Error::InputClosed, Error::ConsoleClosed);
+        assert_eq!(Error::InvalidSize(0, 1), Error::InvalidSize(0, 1));
+        assert_ne!(Error::InvalidSize(0, 1), Error::InvalidSize(1, 0));
+    }
+}
@@ -0,0 +1,217 @@
+//! Module contains [InputRecord]s which can be sent to a [crate::Process].
+//!
+//! ConPTY accepts only a byte stream as an input,
+//! so the records are encoded as VT sequences:
+//!
+//! - [InputRecord::Key] is encoded in win32-input-mode `ESC [ Vk ; Sc ; Uc ; Kd ; Cs ; Rc _`,
+//!   which keeps all the information of a `KEY_EVENT_RECORD`.
+//!   It's best used along with [crate::Quirks::WIN32_INPUT_MODE].
+//! - [InputRecord::Mouse] is encoded as a SGR mouse sequence `ESC [ < Cb ; Cx ; Cy M`,
+//!   a child gets it only if it enabled mouse input.
+//! - [InputRecord::Resize] has no VT representation so it's delivered by resizing the pseudo console.
+
+use std::io::Write;
+
+/// InputRecord represents an input event of a console.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputRecord {
+    /// A keyboard event.
+    Key(KeyEvent),
+    /// A mouse event.
+    Mouse(MouseEvent),
+    /// A console window was resized.
+    Resize {
+        /// A number of columns.
+        columns: i16,
+        /// A number of rows.
+        rows: i16,
+    },
+}
+
+impl InputRecord {
+    /// Encodes the record as a VT sequence.
+    ///
+    /// Returns `None` for [InputRecord::Resize] as it has no VT representation.
+    pub fn to_vt(&self) -> Option<Vec<u8>> {
+        let mut buf = Vec::new();
+        match self {
+            InputRecord::Key(key) => {
+                let _ = write!(
+                    buf,
+                    "\x1b[{};{};{};{};{};{}_",
+                    key.virtual_key_code,
+                    key.virtual_scan_code,
+                    key.unicode_char,
+                    key.key_down as u8,
+                    key.control_key_state,
+                    key.repeat_count
+                );
+            }
+            InputRecord::Mouse(mouse) => {
+                let (button, suffix) = match mouse.kind {
+                    MouseEventKind::Press(button) => (button as u32, 'M'),
+                    MouseEventKind::Release(button) => (button as u32, 'm'),
+                    MouseEventKind::Drag(button) => (button as u32 + 32, 'M'),
+                    MouseEventKind::Move => (3 + 32, 'M'),
+                    MouseEventKind::ScrollUp => (64, 'M'),
+                    MouseEventKind::ScrollDown => (65, 'M'),
+                };
+
+                let mut modifiers = 0;
+                if mouse.control_key_state & SHIFT_PRESSED != 0 {
+                    modifiers += 4;
+                }
+                if mouse.control_key_state & ALT_PRESSED != 0 {
+                    modifiers += 8;
+                }
+                if mouse.control_key_state & CTRL_PRESSED != 0 {
+                    modifiers += 16;
+                }
+
+                // SGR coordinates are 1-based
+                let _ = write!(
+                    buf,
+                    "\x1b[<{};{};{}{}",
+                    button + modifiers,
+                    mouse.column + 1,
+                    mouse.row + 1,
+                    suffix
+                );
+            }
+            InputRecord::Resize { .. } => return None,
+        }
+
+        Some(buf)
+    }
+}
+
+/// KeyEvent mirrors a `KEY_EVENT_RECORD`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// Whether a key is pressed or released.
+    pub key_down: bool,
+    /// A number of times the key was repeated.
+    pub repeat_count: u16,
+    /// A virtual key code (e.g. `VK_RETURN`).
+    pub virtual_key_code: u16,
+    /// A virtual scan code.
+    pub virtual_scan_code: u16,
+    /// A UTF-16 code unit of a translated character or 0.
+    pub unicode_char: u16,
+    /// A state of control keys (e.g. `LEFT_CTRL_PRESSED`).
+    pub control_key_state: u32,
+}
+
+/// MouseEvent represents a mouse event at a given cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    /// A 0-based column.
+    pub column: u16,
+    /// A 0-based row.
+    pub row: u16,
+    /// A kind of the event.
+    pub kind: MouseEventKind,
+    /// A state of control keys (e.g. `SHIFT_PRESSED`).
+    pub control_key_state: u32,
+}
+
+/// MouseEventKind is a kind of [MouseEvent].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseEventKind {
+    /// A button was pressed.
+    Press(MouseButton),
+    /// A button was released.
+    Release(MouseButton),
+    /// A mouse was moved while a button is pressed.
+    Drag(MouseButton),
+    /// A mouse was moved without a pressed button.
+    Move,
+    /// A wheel was scrolled up.
+    ScrollUp,
+    /// A wheel was scrolled down.
+    ScrollDown,
+}
+
+/// MouseButton is a button of a mouse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    /// The left button.
+    Left = 0,
+    /// The middle button.
+    Middle = 1,
+    /// The right button.
+    Right = 2,
+}
+
+// control key state flags of a KEY_EVENT_RECORD
+const SHIFT_PRESSED: u32 = 0x0010;
+const ALT_PRESSED: u32 = 0x0001 | 0x0002;
+const CTRL_PRESSED: u32 = 0x0004 | 0x0008;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_encoding_test() {
+        let record = InputRecord::Key(KeyEvent {
+            key_down: true,
+            repeat_count: 1,
+            virtual_key_code: 0x41,
+            virtual_scan_code: 0x1E,
+            unicode_char: 'a' as u16,
+            control_key_state: 0,
+        });
+
+        assert_eq!(record.to_vt().unwrap(), b"\x1b[65;30;97;1;0;1_");
+    }
+
+    #[test]
+    fn mouse_encoding_test() {
+        let tests = [
+            (MouseEventKind::Press(MouseButton::Left), 0, "\x1b[<0;1;1M"),
+            (
+                MouseEventKind::Release(MouseButton::Right),
+                0,
+                "\x1b[<2;1;1m",
+            ),
+            (MouseEventKind::Drag(MouseButton::Left), 0, "\x1b[<32;1;1M"),
+            (MouseEventKind::Move, 0, "\x1b[<35;1;1M"),
+            (MouseEventKind::ScrollUp, 0, "\x1b[<64;1;1M"),
+            (MouseEventKind::ScrollDown, 0x0008, "\x1b[<81;1;1M"),
+            (
+                MouseEventKind::Press(MouseButton::Middle),
+                0x0010,
+                "\x1b[<5;1;1M",
+            ),
+        ];
+
+        for (kind, control_key_state, expected) in tests {
+            let record = InputRecord::Mouse(MouseEvent {
+                column: 0,
+                row: 0,
+                kind,
+                control_key_state,
+            });
+
+            assert_eq!(record.to_vt().unwrap(), expected.as_bytes(), "{:?}", kind);
+        }
+
+        let record = InputRecord::Mouse(MouseEvent {
+            column: 9,
+            row: 4,
+            kind: MouseEventKind::Press(MouseButton::Left),
+            control_key_state: 0,
+        });
+        assert_eq!(record.to_vt().unwrap(), b"\x1b[<0;10;5M");
+    }
+
+    #[test]
+    fn resize_encoding_test() {
+        let record = InputRecord::Resize {
+            columns: 80,
+            rows: 25,
+        };
+        assert_eq!(record.to_vt(), None);
+    }
+}
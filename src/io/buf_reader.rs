@@ -0,0 +1,128 @@
+use std::io::{self, Read};
+
+use super::PipeReader;
+
+const CHUNK_SIZE: usize = 4096;
+
+/// BufferedPipeReader reads lines from a [PipeReader] which is in a non blocking mode.
+///
+/// Unlike [std::io::BufReader] it doesn't lose a partially read line on [io::ErrorKind::WouldBlock],
+/// the data is kept in an internal buffer until the line is complete.
+///
+/// ```ignore
+/// let mut proc = conpty::spawn("cmd").unwrap();
+/// let mut reader = proc.output().unwrap();
+/// reader.blocking(false);
+///
+/// let mut reader = conpty::io::BufferedPipeReader::new(reader);
+/// loop {
+///     match reader.read_available_line().unwrap() {
+///         Some(line) => print!("{}", line),
+///         None => { /* do something else */ }
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct BufferedPipeReader<R = PipeReader> {
+    reader: R,
+    buf: Vec<u8>,
+    eof: bool,
+}
+
+impl<R: Read> BufferedPipeReader<R> {
+    /// Creates a new buffered reader.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: Vec::new(),
+            eof: false,
+        }
+    }
+
+    /// Reads a line if it's available.
+    ///
+    /// The line is returned with its line ending, like [std::io::BufRead::read_line].
+    /// `None` is returned if the line is not complete yet and the reader would block,
+    /// the partial line is kept for a next call.
+    ///
+    /// On EOF (or a broken pipe) the rest of the data is returned as a last line,
+    /// after which an empty line is returned.
+    pub fn read_available_line(&mut self) -> io::Result<Option<String>> {
+        loop {
+            if let Some(pos) = self.buf.iter().position(|&b| b == b'\n') {
+                let line = self.buf.drain(..=pos).collect::<Vec<_>>();
+                return Ok(Some(String::from_utf8_lossy(&line).into_owned()));
+            }
+
+            if self.eof {
+                let line = String::from_utf8_lossy(&self.buf).into_owned();
+                self.buf.clear();
+                return Ok(Some(line));
+            }
+
+            let mut chunk = [0; CHUNK_SIZE];
+            match self.reader.read(&mut chunk) {
+                Ok(0) => self.eof = true,
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Returns the data which is buffered but not returned yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    ///
+    /// Reading from it directly skips the buffered data.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Returns the underlying reader.
+    ///
+    /// The buffered data is lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::chunks::Chunks;
+
+    #[test]
+    fn read_available_line_test() {
+        let reader = Chunks::with_blocks(vec![
+            Some(&b"Hel"[..]),
+            None,
+            Some(&b"lo\r\nWor"[..]),
+            None,
+            Some(&b"ld"[..]),
+        ]);
+        let mut reader = BufferedPipeReader::new(reader);
+
+        assert_eq!(reader.read_available_line().unwrap(), None);
+        assert_eq!(reader.buffer(), b"Hel");
+        assert_eq!(
+            reader.read_available_line().unwrap().as_deref(),
+            Some("Hello\r\n")
+        );
+        assert_eq!(reader.read_available_line().unwrap(), None);
+        assert_eq!(
+            reader.read_available_line().unwrap().as_deref(),
+            Some("World")
+        );
+        assert_eq!(reader.read_available_line().unwrap().as_deref(), Some(""));
+    }
+}
@@ -0,0 +1,118 @@
+use std::io::{self, Write};
+
+use super::PipeWriter;
+
+const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+/// BufPipeWriter batches small writes to a [PipeWriter].
+///
+/// The buffer is flushed when a newline is written, when it's full,
+/// on [Write::flush] and on drop, so no input is left behind.
+/// It reduces a number of `WriteFile` calls when keystrokes or large pastes are streamed.
+///
+/// If writing the buffer fails the data which wasn't written is kept in the buffer,
+/// like [std::io::BufWriter] does, so the write can be retried.
+#[derive(Debug)]
+pub struct BufPipeWriter {
+    writer: PipeWriter,
+    buf: Vec<u8>,
+    capacity: usize,
+}
+
+impl BufPipeWriter {
+    /// Creates a writer with a default buffer size (8 KiB).
+    pub fn new(writer: PipeWriter) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, writer)
+    }
+
+    /// Creates a writer with a given buffer size.
+    pub fn with_capacity(capacity: usize, writer: PipeWriter) -> Self {
+        Self {
+            writer,
+            buf: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &PipeWriter {
+        &self.writer
+    }
+
+    /// Returns the data which is buffered but not yet written.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    fn flush_buf(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+
+        let mut written = 0;
+        let result = loop {
+            if written == self.buf.len() {
+                break Ok(());
+            }
+
+            match self.writer.write(&self.buf[written..]) {
+                Ok(0) => {
+                    break Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write the buffered data",
+                    ))
+                }
+                Ok(n) => written += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => break Err(err),
+            }
+        };
+
+        let _ = self.buf.drain(..written);
+
+        result
+    }
+}
+
+impl Write for BufPipeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buf.len() + buf.len() > self.capacity {
+            self.flush_buf()?;
+        }
+
+        if buf.len() >= self.capacity {
+            return self.writer.write(buf);
+        }
+
+        let buffered = self.buf.len();
+        self.buf.extend_from_slice(buf);
+
+        if buf.contains(&b'\n') {
+            if let Err(err) = self.flush_buf() {
+                // only the data of previous calls is kept,
+                // so a call either fails without writing anything or reports a partial write
+                let written = buffered + buf.len() - self.buf.len();
+                if written <= buffered {
+                    self.buf.truncate(buffered - written);
+                    return Err(err);
+                }
+
+                self.buf.clear();
+                return Ok(written - buffered);
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_buf()?;
+        self.writer.flush()
+    }
+}
+
+impl Drop for BufPipeWriter {
+    fn drop(&mut self) {
+        let _ = self.flush_buf();
+    }
+}
@@ -0,0 +1,42 @@
+use std::io::{self, Read};
+
+/// Chunks is a fake [Read] which returns the given chunks one by one.
+///
+/// A chunk which doesn't fit into a buffer is returned over several reads
+/// and `None` stands for a [io::ErrorKind::WouldBlock] error.
+pub(crate) struct Chunks<'a>(Vec<Option<&'a [u8]>>);
+
+impl<'a> Chunks<'a> {
+    pub(crate) fn new(chunks: Vec<&'a [u8]>) -> Self {
+        Self(chunks.into_iter().map(Some).collect())
+    }
+
+    pub(crate) fn with_blocks(chunks: Vec<Option<&'a [u8]>>) -> Self {
+        Self(chunks)
+    }
+}
+
+impl Read for Chunks<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.0.is_empty() {
+            return Ok(0);
+        }
+
+        let chunk = match &mut self.0[0] {
+            Some(chunk) => chunk,
+            None => {
+                let _ = self.0.remove(0);
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, ""));
+            }
+        };
+
+        let n = chunk.len().min(buf.len());
+        buf[..n].copy_from_slice(&chunk[..n]);
+        *chunk = &chunk[n..];
+        if chunk.is_empty() {
+            let _ = self.0.remove(0);
+        }
+
+        Ok(n)
+    }
+}
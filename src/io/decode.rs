@@ -0,0 +1,242 @@
+use std::io::{self, Read};
+
+use windows::Win32::Globalization::{
+    IsDBCSLeadByteEx, MultiByteToWideChar, CP_ACP, MULTI_BYTE_TO_WIDE_CHAR_FLAGS,
+};
+
+use super::PipeReader;
+
+const CHUNK_SIZE: usize = 4096;
+
+/// Encoding of an output of a process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// UTF-8.
+    Utf8,
+    /// UTF-16 little endian, which is used by e.g. `cmd /U` and some PowerShell cmdlets.
+    Utf16Le,
+    /// A system ANSI code page (`CP_ACP`), which is used by legacy applications.
+    Ansi,
+}
+
+/// DecodingReader decodes an output in a given [Encoding] to UTF-8.
+///
+/// Characters which are split across reads are buffered until they're complete,
+/// invalid sequences are replaced with `U+FFFD`.
+/// An incomplete character at EOF is replaced with `U+FFFD` as well.
+#[derive(Debug)]
+pub struct DecodingReader<R = PipeReader> {
+    reader: R,
+    encoding: Encoding,
+    // bytes of an incomplete character
+    pending: Vec<u8>,
+    // decoded bytes which weren't returned yet
+    decoded: Vec<u8>,
+    pos: usize,
+}
+
+impl<R> DecodingReader<R> {
+    /// Creates a new decoding reader.
+    pub fn new(reader: R, encoding: Encoding) -> Self {
+        Self {
+            reader,
+            encoding,
+            pending: Vec::new(),
+            decoded: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Returns an encoding of the reader.
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Returns the underlying reader.
+    ///
+    /// The data which was decoded but not read yet is lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.decoded.len() {
+            self.decoded.clear();
+            self.pos = 0;
+
+            let mut chunk = [0; CHUNK_SIZE];
+            let n = self.reader.read(&mut chunk)?;
+            if n == 0 {
+                if self.pending.is_empty() {
+                    return Ok(0);
+                }
+
+                // an incomplete character at EOF
+                self.pending.clear();
+                self.decoded
+                    .extend(char::REPLACEMENT_CHARACTER.to_string().bytes());
+                break;
+            }
+
+            self.pending.extend_from_slice(&chunk[..n]);
+            let rest = decode(self.encoding, &self.pending, &mut self.decoded);
+            let _ = self.pending.drain(..self.pending.len() - rest);
+        }
+
+        let n = std::cmp::min(buf.len(), self.decoded.len() - self.pos);
+        buf[..n].copy_from_slice(&self.decoded[self.pos..self.pos + n]);
+        self.pos += n;
+
+        Ok(n)
+    }
+}
+
+impl PipeReader {
+    /// Wraps the reader into a [DecodingReader] which yields UTF-8.
+    pub fn with_encoding(self, encoding: Encoding) -> DecodingReader {
+        DecodingReader::new(self, encoding)
+    }
+}
+
+// decodes as much of the input as possible into `out`,
+// returns a number of bytes at the end which make an incomplete character.
+fn decode(encoding: Encoding, input: &[u8], out: &mut Vec<u8>) -> usize {
+    match encoding {
+        Encoding::Utf8 => decode_utf8(input, out),
+        Encoding::Utf16Le => decode_utf16le(input, out),
+        Encoding::Ansi => decode_ansi(input, out),
+    }
+}
+
+fn decode_utf8(mut input: &[u8], out: &mut Vec<u8>) -> usize {
+    loop {
+        match std::str::from_utf8(input) {
+            Ok(s) => {
+                out.extend_from_slice(s.as_bytes());
+                return 0;
+            }
+            Err(err) => {
+                let (valid, rest) = input.split_at(err.valid_up_to());
+                out.extend_from_slice(valid);
+
+                match err.error_len() {
+                    Some(len) => {
+                        out.extend(char::REPLACEMENT_CHARACTER.to_string().bytes());
+                        input = &rest[len..];
+                    }
+                    None => return rest.len(),
+                }
+            }
+        }
+    }
+}
+
+fn decode_utf16le(input: &[u8], out: &mut Vec<u8>) -> usize {
+    let mut units = input
+        .chunks_exact(2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .collect::<Vec<_>>();
+    let mut rest = input.len() % 2;
+
+    // a high surrogate waits for its pair
+    if let Some(&last) = units.last() {
+        if (0xD800..0xDC00).contains(&last) {
+            let _ = units.pop();
+            rest += 2;
+        }
+    }
+
+    let s = String::from_utf16_lossy(&units);
+    out.extend_from_slice(s.as_bytes());
+
+    rest
+}
+
+fn decode_ansi(input: &[u8], out: &mut Vec<u8>) -> usize {
+    // a lead byte of a double byte character waits for its pair
+    let mut i = 0;
+    while i < input.len() {
+        let lead = unsafe { IsDBCSLeadByteEx(CP_ACP, input[i]).is_ok() };
+        i += if lead { 2 } else { 1 };
+    }
+    let rest = i - input.len();
+    let input = &input[..input.len() - rest];
+
+    if input.is_empty() {
+        return rest;
+    }
+
+    let flags = MULTI_BYTE_TO_WIDE_CHAR_FLAGS(0);
+    let size = unsafe { MultiByteToWideChar(CP_ACP, flags, input, None) };
+    let mut wide = vec![0; size as usize];
+    let size = unsafe { MultiByteToWideChar(CP_ACP, flags, input, Some(&mut wide)) };
+    wide.truncate(size as usize);
+
+    let s = String::from_utf16_lossy(&wide);
+    out.extend_from_slice(s.as_bytes());
+
+    rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::chunks::Chunks;
+
+    #[test]
+    fn utf8_test() {
+        let text = "Hello 😁 World";
+        for split in 1..text.len() {
+            let mut reader = DecodingReader::new(
+                Chunks::new(vec![&text.as_bytes()[..split], &text.as_bytes()[split..]]),
+                Encoding::Utf8,
+            );
+            let mut out = String::new();
+            let _ = reader.read_to_string(&mut out).unwrap();
+            assert_eq!(out, text);
+        }
+
+        let mut out = Vec::new();
+        assert_eq!(decode_utf8(b"a\xFFb\xF0\x9F", &mut out), 2);
+        assert_eq!(out, "a\u{FFFD}b".as_bytes());
+    }
+
+    #[test]
+    fn utf16le_test() {
+        let text = "Hello 😁 World";
+        let bytes = text
+            .encode_utf16()
+            .flat_map(|u| u.to_le_bytes())
+            .collect::<Vec<_>>();
+
+        for split in 1..bytes.len() {
+            let mut reader = DecodingReader::new(
+                Chunks::new(vec![&bytes[..split], &bytes[split..]]),
+                Encoding::Utf16Le,
+            );
+            let mut out = String::new();
+            let _ = reader.read_to_string(&mut out).unwrap();
+            assert_eq!(out, text);
+        }
+    }
+
+    #[test]
+    fn incomplete_at_eof_test() {
+        let mut reader = DecodingReader::new(Chunks::new(vec![b"a\xF0\x9F"]), Encoding::Utf8);
+        let mut out = String::new();
+        let _ = reader.read_to_string(&mut out).unwrap();
+        assert_eq!(out, "a\u{FFFD}");
+    }
+}
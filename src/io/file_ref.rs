@@ -0,0 +1,34 @@
+use std::{fs::File, marker::PhantomData, mem::ManuallyDrop, ops::Deref};
+
+/// FileRef is a [File] which borrows a HANDLE of a pipe.
+///
+/// It's created by [super::PipeReader::borrow_as_file].
+/// The HANDLE is not closed on drop, it's still owned by the pipe,
+/// and the lifetime makes sure the [FileRef] doesn't outlive the pipe.
+///
+/// [File] implements [std::io::Read] for `&File`, so it can be read through a reference.
+/// Beware that a [File] read doesn't respect a non blocking mode of the pipe
+/// and a broken pipe is reported as an error.
+#[derive(Debug)]
+pub struct FileRef<'a> {
+    file: ManuallyDrop<File>,
+    _pipe: PhantomData<&'a ()>,
+}
+
+impl FileRef<'_> {
+    // The file must be made from a HANDLE which outlives the FileRef and must not be closed.
+    pub(crate) fn new(file: File) -> Self {
+        Self {
+            file: ManuallyDrop::new(file),
+            _pipe: PhantomData,
+        }
+    }
+}
+
+impl Deref for FileRef<'_> {
+    type Target = File;
+
+    fn deref(&self) -> &File {
+        &self.file
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use blocking::Unblock;
+use futures_io::{AsyncRead, AsyncWrite};
+
+use super::{PipeReader, PipeWriter};
+
+/// FuturesPipeReader implements [futures_io::AsyncRead] for a [PipeReader].
+///
+/// Reads are offloaded to a thread pool of the `blocking` crate,
+/// so it can be used with any executor (e.g. `smol`, `async-std` or `futures::executor`).
+/// The reader must be in a blocking mode.
+///
+/// ```ignore
+/// use futures::AsyncReadExt;
+///
+/// let mut proc = conpty::spawn("echo Hello World").unwrap();
+/// let mut reader = conpty::io::FuturesPipeReader::new(proc.output().unwrap());
+///
+/// futures::executor::block_on(async {
+///     let mut buf = [0; 1024];
+///     let n = reader.read(&mut buf).await.unwrap();
+/// });
+/// ```
+#[derive(Debug)]
+pub struct FuturesPipeReader(Unblock<PipeReader>);
+
+impl FuturesPipeReader {
+    /// Creates a new reader.
+    pub fn new(reader: PipeReader) -> Self {
+        Self(Unblock::new(reader))
+    }
+}
+
+impl AsyncRead for FuturesPipeReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+/// FuturesPipeWriter implements [futures_io::AsyncWrite] for a [PipeWriter].
+///
+/// Writes are offloaded to a thread pool of the `blocking` crate.
+/// The data may be buffered, so [futures_io::AsyncWrite::poll_flush] must be called
+/// to make sure it was written to the pipe.
+#[derive(Debug)]
+pub struct FuturesPipeWriter(Unblock<PipeWriter>);
+
+impl FuturesPipeWriter {
+    /// Creates a new writer.
+    pub fn new(writer: PipeWriter) -> Self {
+        Self(Unblock::new(writer))
+    }
+}
+
+impl AsyncWrite for FuturesPipeWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_close(cx)
+    }
+}
@@ -0,0 +1,120 @@
+use std::io::{self, Read};
+
+use super::{strip::VtStripper, DecodingReader, Encoding, PipeReader};
+
+const CHUNK_SIZE: usize = 4096;
+
+/// TextLines is an iterator over lines of an output with VT sequences stripped.
+///
+/// The output is decoded as UTF-8 (which is what ConPTY emits),
+/// so a character or a VT sequence split across reads is handled.
+/// Lines are returned without a trailing `\r\n` or `\n`.
+/// VT sequences can be kept by creating it with [TextLines::with_vt].
+///
+/// The iterator ends on EOF or a broken pipe.
+#[derive(Debug)]
+pub struct TextLines<R = PipeReader> {
+    reader: DecodingReader<R>,
+    stripper: VtStripper,
+    buf: Vec<u8>,
+    eof: bool,
+    strip: bool,
+}
+
+impl<R: Read> TextLines<R> {
+    /// Creates an iterator over lines of a reader.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: DecodingReader::new(reader, Encoding::Utf8),
+            stripper: VtStripper::new(),
+            buf: Vec::new(),
+            eof: false,
+            strip: true,
+        }
+    }
+
+    /// Creates an iterator over lines of a reader which keeps VT sequences.
+    pub fn with_vt(reader: R) -> Self {
+        Self {
+            strip: false,
+            ..Self::new(reader)
+        }
+    }
+
+    /// Returns the underlying reader.
+    ///
+    /// The data which was read but not returned yet is lost.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+
+    fn take_line(&mut self) -> Option<String> {
+        let pos = self.buf.iter().position(|&b| b == b'\n')?;
+        let mut line = self.buf.drain(..=pos).collect::<Vec<_>>();
+        let _ = line.pop();
+        if line.last() == Some(&b'\r') {
+            let _ = line.pop();
+        }
+
+        Some(String::from_utf8_lossy(&line).into_owned())
+    }
+}
+
+impl<R: Read> Iterator for TextLines<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(line) = self.take_line() {
+                return Some(Ok(line));
+            }
+
+            if self.eof {
+                if self.buf.is_empty() {
+                    return None;
+                }
+
+                let line = String::from_utf8_lossy(&self.buf).into_owned();
+                self.buf.clear();
+                return Some(Ok(line));
+            }
+
+            let mut chunk = [0; CHUNK_SIZE];
+            match self.reader.read(&mut chunk) {
+                Ok(0) => self.eof = true,
+                Ok(n) if self.strip => self.stripper.strip(&chunk[..n], &mut self.buf),
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::chunks::Chunks;
+
+    #[test]
+    fn text_lines_test() {
+        let output = "\x1b[?25lHello\r\n\x1b[31mWorld 😁\x1b[0m\r\nlast".as_bytes();
+        for split in 1..output.len() {
+            let reader = Chunks::new(vec![&output[..split], &output[split..]]);
+            let lines = TextLines::new(reader)
+                .collect::<io::Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(lines, ["Hello", "World 😁", "last"]);
+        }
+    }
+
+    #[test]
+    fn text_lines_with_vt_test() {
+        let output = "\x1b[?25lHello\r\n\x1b[31mWorld\x1b[0m\r\nlast".as_bytes();
+        let reader = Chunks::new(vec![output]);
+        let lines = TextLines::with_vt(reader)
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(lines, ["\x1b[?25lHello", "\x1b[31mWorld\x1b[0m", "last"]);
+    }
+}
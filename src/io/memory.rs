@@ -0,0 +1,113 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    sync::{Arc, Condvar, Mutex},
+};
+
+use super::PtyIo;
+
+/// MemoryPipe is an in-memory fake of [super::PipeReader] and [super::PipeWriter].
+///
+/// Everything written to the pipe can be read back from it.
+/// All cloned instances share the same buffer,
+/// so one clone can be used as a reader and another one as a writer.
+///
+/// A read blocks until some data is written or the pipe is closed by [MemoryPipe::close],
+/// after which reads return EOF once the buffer is drained.
+#[derive(Debug, Clone)]
+pub struct MemoryPipe {
+    shared: Arc<Shared>,
+    blocking: bool,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    state: Mutex<State>,
+    cond: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    buf: VecDeque<u8>,
+    closed: bool,
+}
+
+impl MemoryPipe {
+    /// Creates a new empty pipe.
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::default(),
+            blocking: true,
+        }
+    }
+
+    /// Sets a pipe to a non blocking mode.
+    ///
+    /// It affects only this instance, like [super::PipeReader::blocking].
+    pub fn blocking(&mut self, on: bool) {
+        self.blocking = on;
+    }
+
+    /// Closes the pipe for all instances.
+    ///
+    /// Reads return EOF after the data left in the pipe is read,
+    /// writes return [io::ErrorKind::BrokenPipe].
+    pub fn close(&self) {
+        self.shared.state.lock().unwrap().closed = true;
+        self.shared.cond.notify_all();
+    }
+
+    /// Returns a number of bytes which can be read without blocking.
+    pub fn bytes_available(&self) -> usize {
+        self.shared.state.lock().unwrap().buf.len()
+    }
+}
+
+impl Default for MemoryPipe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Read for MemoryPipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut state = self.shared.state.lock().unwrap();
+        while state.buf.is_empty() && !state.closed {
+            if !self.blocking {
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, ""));
+            }
+
+            state = self.shared.cond.wait(state).unwrap();
+        }
+
+        let n = state.buf.read(buf)?;
+        Ok(n)
+    }
+}
+
+impl Write for MemoryPipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.closed {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "The pipe is closed",
+            ));
+        }
+
+        state.buf.extend(buf);
+        self.shared.cond.notify_all();
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl PtyIo for MemoryPipe {
+    fn try_clone_io(&self) -> io::Result<Self> {
+        Ok(self.clone())
+    }
+}
@@ -2,9 +2,90 @@
 //!
 //! Input - PipeWriter
 //! Output - PipeReader
+//!
+//! The pipes are direction safe.
+//! [PipeWriter] implements only [std::io::Write] and [PipeReader] implements only [std::io::Read],
+//! so passing a pipe of a wrong direction is a compile time error.
+//!
+//! ```compile_fail
+//! use std::io::Read;
+//!
+//! fn read_input(mut input: conpty::io::PipeWriter) {
+//!     input.read(&mut [0; 128]).unwrap();
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use std::io::Write;
+//!
+//! fn write_output(mut output: conpty::io::PipeReader) {
+//!     output.write_all(b"Hello World").unwrap();
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! let (_waiter, output) = conpty::spawn_output("echo Hello World").unwrap();
+//!
+//! let input: conpty::io::PipeWriter = output;
+//! ```
 
+mod buf_reader;
+mod buf_writer;
+#[cfg(test)]
+mod chunks;
+mod decode;
+mod file_ref;
+#[cfg(feature = "async-io")]
+mod futures_io;
+mod lines;
+#[cfg(feature = "test-util")]
+mod memory;
+mod overlapped;
+mod plain;
 mod reader;
+mod strip;
+#[cfg(feature = "tokio")]
+mod tokio_io;
 mod writer;
 
+pub use buf_reader::BufferedPipeReader;
+pub use buf_writer::BufPipeWriter;
+pub use decode::{DecodingReader, Encoding};
+pub use file_ref::FileRef;
+#[cfg(feature = "async-io")]
+pub use futures_io::{FuturesPipeReader, FuturesPipeWriter};
+pub use lines::TextLines;
+#[cfg(feature = "test-util")]
+pub use memory::MemoryPipe;
+pub use overlapped::OverlappedRead;
+pub use plain::PlainTextReader;
 pub use reader::PipeReader;
+#[cfg(feature = "tokio")]
+pub use tokio_io::{AsyncPipeReader, AsyncPipeWriter};
 pub use writer::PipeWriter;
+
+pub(crate) use strip::strip_vt;
+
+/// PtyIo is a common interface of pseudo console IO endpoints.
+///
+/// Code written against `R: Read + PtyIo` or `W: Write + PtyIo`
+/// can be tested with [MemoryPipe] instead of real pipes.
+#[cfg(feature = "test-util")]
+pub trait PtyIo: Send + Sized {
+    /// Tries to make a new instance which shares the same underlying stream.
+    fn try_clone_io(&self) -> std::io::Result<Self>;
+}
+
+#[cfg(feature = "test-util")]
+impl PtyIo for PipeReader {
+    fn try_clone_io(&self) -> std::io::Result<Self> {
+        self.try_clone().map_err(Into::into)
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl PtyIo for PipeWriter {
+    fn try_clone_io(&self) -> std::io::Result<Self> {
+        self.try_clone().map_err(Into::into)
+    }
+}
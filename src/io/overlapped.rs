@@ -0,0 +1,183 @@
+use std::{ffi::c_void, fmt, io, os::windows::io::RawHandle};
+
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::{ERROR_BROKEN_PIPE, ERROR_IO_INCOMPLETE, ERROR_IO_PENDING, HANDLE},
+        Storage::FileSystem::{ReadFile, WriteFile},
+        System::{
+            Threading::CreateEventW,
+            IO::{CancelIoEx, GetOverlappedResult, OVERLAPPED},
+        },
+    },
+};
+
+use crate::util::{clone_handle, OwnedHandle};
+
+/// OverlappedRead is a read which is in progress.
+///
+/// It's created by [super::PipeReader::read_overlapped].
+/// The read is completed by the system in background,
+/// [Self::event] can be used to wait for the completion together with other objects
+/// (e.g. by `WaitForMultipleObjects` in an event loop).
+///
+/// The read is cancelled on drop if it's not completed yet.
+pub struct OverlappedRead {
+    handle: OwnedHandle,
+    event: OwnedHandle,
+    // the system writes to the buffer and the structure until the read is completed,
+    // so both must not move
+    overlapped: Box<OVERLAPPED>,
+    buf: Vec<u8>,
+    completed: bool,
+}
+
+impl OverlappedRead {
+    pub(crate) fn start(handle: HANDLE, size: usize) -> io::Result<Self> {
+        let handle = OwnedHandle(clone_handle(handle)?);
+        let event = OwnedHandle(unsafe { CreateEventW(None, true, false, PCWSTR::null())? });
+        let overlapped = Box::new(OVERLAPPED {
+            hEvent: event.0,
+            ..Default::default()
+        });
+
+        let mut read = Self {
+            handle,
+            event,
+            overlapped,
+            buf: vec![0; size],
+            completed: false,
+        };
+
+        let overlapped: *mut OVERLAPPED = &mut *read.overlapped;
+        let result =
+            unsafe { ReadFile(read.handle.0, Some(&mut read.buf), None, Some(overlapped)) };
+        match result {
+            Ok(()) => {}
+            Err(err) if err.code() == ERROR_IO_PENDING.to_hresult() => {}
+            // EOF, nothing was queued
+            Err(err) if err.code() == ERROR_BROKEN_PIPE.to_hresult() => read.completed = true,
+            Err(err) => {
+                read.completed = true;
+                return Err(err.into());
+            }
+        }
+
+        Ok(read)
+    }
+
+    /// Returns an event which is signaled once the read is completed.
+    ///
+    /// The event is owned by the [OverlappedRead].
+    pub fn event(&self) -> RawHandle {
+        self.event.0 .0 as RawHandle
+    }
+
+    /// Checks whether the read is completed, returning the read data if so.
+    ///
+    /// `None` is returned if the read is still in progress.
+    /// An empty buffer means EOF, it's also returned once the data was taken.
+    pub fn poll(&mut self) -> io::Result<Option<Vec<u8>>> {
+        self.result(false)
+    }
+
+    /// Waits for the read to complete, returning the read data.
+    ///
+    /// An empty buffer means EOF.
+    pub fn wait(mut self) -> io::Result<Vec<u8>> {
+        self.result(true).map(Option::unwrap_or_default)
+    }
+
+    fn result(&mut self, wait: bool) -> io::Result<Option<Vec<u8>>> {
+        if self.completed {
+            return Ok(Some(Vec::new()));
+        }
+
+        let mut n = 0;
+        let result = unsafe { GetOverlappedResult(self.handle.0, &*self.overlapped, &mut n, wait) };
+        let n = match result {
+            Ok(()) => n as usize,
+            Err(err) if err.code() == ERROR_IO_INCOMPLETE.to_hresult() => return Ok(None),
+            Err(err) if err.code() == ERROR_BROKEN_PIPE.to_hresult() => 0,
+            Err(err) => {
+                self.completed = true;
+                return Err(err.into());
+            }
+        };
+
+        self.completed = true;
+        let mut buf = std::mem::take(&mut self.buf);
+        buf.truncate(n);
+
+        Ok(Some(buf))
+    }
+}
+
+impl Drop for OverlappedRead {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+
+        // the buffer can be released only once the system doesn't use it
+        let mut n = 0;
+        unsafe {
+            let _ = CancelIoEx(self.handle.0, Some(&*self.overlapped));
+            let _ = GetOverlappedResult(self.handle.0, &*self.overlapped, &mut n, true);
+        }
+    }
+}
+
+impl fmt::Debug for OverlappedRead {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OverlappedRead")
+            .field("handle", &(self.handle.0 .0 as *const c_void))
+            .field("event", &(self.event.0 .0 as *const c_void))
+            .field("completed", &self.completed)
+            .finish()
+    }
+}
+
+unsafe impl Send for OverlappedRead {}
+
+// Reads from a pipe opened with FILE_FLAG_OVERLAPPED, waiting for the completion.
+pub(crate) fn read_overlapped(h: HANDLE, buf: &mut [u8]) -> io::Result<usize> {
+    let event = OwnedHandle(unsafe { CreateEventW(None, true, false, PCWSTR::null())? });
+    let mut overlapped = OVERLAPPED {
+        hEvent: event.0,
+        ..Default::default()
+    };
+
+    let result = unsafe { ReadFile(h, Some(buf), None, Some(&mut overlapped)) };
+    complete(h, &overlapped, result)
+}
+
+// Writes to a pipe opened with FILE_FLAG_OVERLAPPED, waiting for the completion.
+pub(crate) fn write_overlapped(h: HANDLE, buf: &[u8]) -> io::Result<usize> {
+    let event = OwnedHandle(unsafe { CreateEventW(None, true, false, PCWSTR::null())? });
+    let mut overlapped = OVERLAPPED {
+        hEvent: event.0,
+        ..Default::default()
+    };
+
+    let result = unsafe { WriteFile(h, Some(buf), None, Some(&mut overlapped)) };
+    complete(h, &overlapped, result)
+}
+
+fn complete(
+    h: HANDLE,
+    overlapped: &OVERLAPPED,
+    result: windows::core::Result<()>,
+) -> io::Result<usize> {
+    match result {
+        Ok(()) => {}
+        Err(err) if err.code() == ERROR_IO_PENDING.to_hresult() => {}
+        Err(err) => return Err(err.into()),
+    }
+
+    // it waits for the completion, as the operation uses the stack
+    let mut n = 0;
+    unsafe { GetOverlappedResult(h, overlapped, &mut n, true)? };
+
+    Ok(n as usize)
+}
@@ -0,0 +1,111 @@
+use std::io::{self, Read};
+
+use super::{strip::VtStripper, PipeReader};
+
+/// PlainTextReader removes VT sequences (CSI, OSC and others) from an output on the fly.
+///
+/// A sequence which is split across reads is handled,
+/// as the state of a partially read sequence is kept between [Read::read] calls.
+///
+/// ```ignore
+/// use std::io::Read;
+///
+/// let mut proc = conpty::spawn("echo Hello World").unwrap();
+/// let mut reader = conpty::io::PlainTextReader::new(proc.output().unwrap());
+///
+/// let mut buf = [0; 1024];
+/// let n = reader.read(&mut buf).unwrap();
+/// assert!(!buf[..n].contains(&b'\x1b'));
+/// ```
+#[derive(Debug)]
+pub struct PlainTextReader<R = PipeReader> {
+    reader: R,
+    stripper: VtStripper,
+    // stripped data which didn't fit into a caller's buffer
+    pending: Vec<u8>,
+    chunk: Vec<u8>,
+}
+
+impl<R: Read> PlainTextReader<R> {
+    /// Creates a new reader.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            stripper: VtStripper::new(),
+            pending: Vec::new(),
+            chunk: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    ///
+    /// Reading from it directly breaks a state of a partially read sequence.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Returns the underlying reader.
+    ///
+    /// The data which was read but not returned yet is lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Read for PlainTextReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // a read which consists only of a sequence must not be reported as EOF
+        while self.pending.is_empty() {
+            self.chunk.resize(buf.len(), 0);
+            let n = self.reader.read(&mut self.chunk)?;
+            if n == 0 {
+                return Ok(0);
+            }
+
+            self.stripper.strip(&self.chunk[..n], &mut self.pending);
+        }
+
+        let n = self.pending.len().min(buf.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        let _ = self.pending.drain(..n);
+
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::chunks::Chunks;
+
+    #[test]
+    fn plain_text_reader_test() {
+        let output = b"\x1b[?25lHello\x1b]0;title\x07 World\x1b[0m";
+        for split in 1..output.len() {
+            let reader = Chunks::new(vec![&output[..split], &output[split..]]);
+            let mut reader = PlainTextReader::new(reader);
+
+            let mut text = Vec::new();
+            let mut buf = [0; 3];
+            loop {
+                let n = reader.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+
+                text.extend_from_slice(&buf[..n]);
+            }
+
+            assert_eq!(text, b"Hello World");
+        }
+    }
+}
@@ -1,24 +1,57 @@
 use std::{
+    any::Any,
     ffi::c_void,
     fmt,
-    io::{self, Read},
+    io::{self, IoSliceMut, Read},
     mem::MaybeUninit,
+    os::windows::io::RawHandle,
     ptr,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
 };
 
 use windows::Win32::{
-    Foundation::{CloseHandle, HANDLE},
+    Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0},
     Storage::FileSystem::ReadFile,
-    System::Pipes::PeekNamedPipe,
+    System::{Pipes::PeekNamedPipe, Threading::WaitForSingleObject},
 };
 
-use crate::{error::Error, util::clone_handle};
+use super::{
+    overlapped::{read_overlapped, OverlappedRead},
+    FileRef,
+};
+use crate::{
+    error::Error,
+    util::{clone_handle, duplicate_handle_into, is_broken_pipe, OwnedHandle},
+};
+
+// An interval with which an exit aware read checks the process and the pipe.
+const EXIT_POLL_INTERVAL_MILLIS: u32 = 10;
+// A time given to the console to flush the output after the process exit.
+const EXIT_FLUSH_TIMEOUT: Duration = Duration::from_millis(100);
 
 /// PipeReader wraps a win32 pipe to provide a [std::io::Read] interface.
 /// It also provides a non_blocking mode settings.
+///
+/// A read returns EOF (`Ok(0)`) once the write end of the pipe is closed (e.g. the console is closed),
+/// a broken pipe is not reported as an error.
 pub struct PipeReader {
     handle: HANDLE,
     blocking: bool,
+    // an object which is kept alive as long as the reader exists
+    keep_alive: Option<Arc<dyn Any + Send + Sync>>,
+    // a process after which exit a blocking read returns EOF
+    exit_watch: Option<Arc<OwnedHandle>>,
+    // the handle was opened with FILE_FLAG_OVERLAPPED
+    overlapped: bool,
+}
+
+// Pipe is a HANDLE with its mode.
+#[derive(Debug, Clone, Copy)]
+struct Pipe {
+    handle: HANDLE,
+    overlapped: bool,
 }
 
 impl PipeReader {
@@ -27,29 +60,258 @@ impl PipeReader {
         Self {
             handle,
             blocking: true,
+            keep_alive: None,
+            exit_watch: None,
+            overlapped: false,
+        }
+    }
+
+    // The handle must be opened with FILE_FLAG_OVERLAPPED.
+    pub(crate) fn overlapped(mut self, on: bool) -> Self {
+        self.overlapped = on;
+        self
+    }
+
+    fn pipe(&self) -> Pipe {
+        Pipe {
+            handle: self.handle,
+            overlapped: self.overlapped,
         }
     }
 
+    pub(crate) fn keep_alive(mut self, object: Arc<dyn Any + Send + Sync>) -> Self {
+        self.keep_alive = Some(object);
+        self
+    }
+
+    // A blocking read returns EOF once the process has exited and the pipe is empty,
+    // even if the write end of the pipe is still held open by some other process.
+    pub(crate) fn exit_aware(mut self, process: HANDLE) -> Result<Self, Error> {
+        let process = clone_handle(process)?;
+        self.exit_watch = Some(Arc::new(OwnedHandle(process)));
+        Ok(self)
+    }
+
+    fn exit_handle(&self) -> Option<HANDLE> {
+        self.exit_watch.as_ref().map(|h| h.0)
+    }
+
     /// Sets a pipe to a non blocking mode.
     ///
     /// It doesn't changes DUPed handles.
+    /// The mode is a flag of the instance rather than a property of the HANDLE,
+    /// so each clone has its own mode.
     ///
     /// Mainly developed to not pile down libraries to include any windows API crate.
     pub fn blocking(&mut self, on: bool) {
         self.blocking = on;
     }
 
+    /// Returns whether the reader is in a blocking mode.
+    pub fn is_blocking(&self) -> bool {
+        self.blocking
+    }
+
+    /// Reads bytes into `buf` until the delimiter `delim` or EOF is reached.
+    ///
+    /// The delimiter is included into `buf`.
+    /// It works like [std::io::BufRead::read_until] but without a [std::io::BufReader],
+    /// the pipe is peeked so the data after the delimiter is left in the pipe.
+    ///
+    /// In a non blocking mode [io::ErrorKind::WouldBlock] is returned
+    /// if the delimiter was not reached yet, the data which was read is kept in `buf`.
+    pub fn read_until(&mut self, delim: u8, buf: &mut Vec<u8>) -> io::Result<usize> {
+        read_pipe_until(self.pipe(), delim, buf, self.blocking, self.exit_handle())
+    }
+
+    /// Returns a number of bytes which can be read without blocking.
+    ///
+    /// The count is a snapshot, it can grow between the call and a subsequent read.
+    /// A non blocking read uses the same check.
+    pub fn bytes_available(&self) -> io::Result<usize> {
+        pipe_available_bytes(self.handle).map(|n| n as usize)
+    }
+
+    /// Reads whatever is available within a `timeout`.
+    ///
+    /// It waits until some data is available and reads it with a single read,
+    /// [io::ErrorKind::TimedOut] is returned if nothing was available in time.
+    /// It works the same way regardless of [Self::blocking] mode.
+    pub fn read_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+        read_pipe_timeout(self.pipe(), buf, timeout)
+    }
+
+    /// Reads exactly enough bytes to fill `buf` within a `timeout`.
+    ///
+    /// It works like [Read::read_exact] but the whole read is limited by the `timeout`,
+    /// [io::ErrorKind::TimedOut] is returned if `buf` wasn't filled in time
+    /// and [io::ErrorKind::UnexpectedEof] if EOF was reached before.
+    /// On error the data which was already read is left in `buf`, but its amount is unspecified.
+    pub fn read_exact_timeout(&mut self, mut buf: &mut [u8], timeout: Duration) -> io::Result<()> {
+        let deadline = Instant::now() + timeout;
+        while !buf.is_empty() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match read_pipe_timeout(self.pipe(), buf, remaining) {
+                Ok(0) => return Err(unexpected_eof()),
+                Ok(n) => buf = &mut buf[n..],
+                Err(err) if is_broken_pipe(&err) => return Err(unexpected_eof()),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "the buffer wasn't filled in time",
+                    ))
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Starts a read in background, returning a handle to the read in progress.
+    ///
+    /// It's available only for a reader of a process spawned with [crate::ProcessOptions::overlapped_io],
+    /// otherwise [io::ErrorKind::Unsupported] is returned.
+    /// At most `size` bytes are read.
+    ///
+    /// ```ignore
+    /// let mut proc = conpty::ProcessOptions::default()
+    ///     .overlapped_io(true)
+    ///     .spawn(std::process::Command::new("cmd /C echo Hello World"))
+    ///     .unwrap();
+    /// let reader = proc.output().unwrap();
+    ///
+    /// let mut read = reader.read_overlapped(1024).unwrap();
+    /// while read.poll().unwrap().is_none() {
+    ///     // do something else
+    /// }
+    /// ```
+    pub fn read_overlapped(&self, size: usize) -> io::Result<OverlappedRead> {
+        if !self.overlapped {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the pipe is not opened for overlapped IO",
+            ));
+        }
+
+        OverlappedRead::start(self.handle, size)
+    }
+
+    /// Returns whether the pipe is opened for overlapped IO.
+    ///
+    /// See [crate::ProcessOptions::overlapped_io].
+    pub fn is_overlapped(&self) -> bool {
+        self.overlapped
+    }
+
+    /// Duplicates the pipe HANDLE into a handle table of a `target` process.
+    ///
+    /// `target` is a process HANDLE which must have `PROCESS_DUP_HANDLE` access right.
+    /// The returned HANDLE is valid only in the target process and it's the target's responsibility to close it.
+    ///
+    /// The HANDLE is duplicated with `DUPLICATE_SAME_ACCESS` so it has the same access rights as the pipe,
+    /// and `DUPLICATE_CLOSE_SOURCE` is not used so the pipe remains usable.
+    pub fn duplicate_into(&self, target: HANDLE) -> Result<RawHandle, Error> {
+        let handle = duplicate_handle_into(self.handle, target)?;
+        Ok(handle.0 as RawHandle)
+    }
+
+    /// Tries to clone a instance to a new one in a given mode.
+    ///
+    /// See [Self::try_clone].
+    pub fn clone_with_blocking(&self, blocking: bool) -> Result<Self, Error> {
+        let mut reader = self.try_clone()?;
+        reader.blocking(blocking);
+        Ok(reader)
+    }
+
+    /// Tries to clone a instance to a new one in a non blocking mode.
+    ///
+    /// See [Self::try_clone].
+    pub fn clone_nonblocking(&self) -> Result<Self, Error> {
+        self.clone_with_blocking(false)
+    }
+
+    /// Returns a [std::fs::File] which borrows the pipe HANDLE.
+    ///
+    /// Unlike `From<PipeReader> for File` the reader is kept,
+    /// the HANDLE is not closed when the [FileRef] is dropped and it can't outlive the reader.
+    /// Reading through the file affects the reader as they share the same pipe.
+    ///
+    /// [std::fs::File] doesn't support overlapped IO,
+    /// so for a reader of a process spawned with [crate::ProcessOptions::overlapped_io]
+    /// [io::ErrorKind::Unsupported] is returned.
+    ///
+    /// ```ignore
+    /// use std::io::Read;
+    ///
+    /// let mut proc = conpty::spawn("echo Hello World").unwrap();
+    /// let reader = proc.output().unwrap();
+    ///
+    /// let file = reader.borrow_as_file().unwrap();
+    /// let mut buf = [0; 1024];
+    /// let n = (&*file).read(&mut buf).unwrap();
+    /// ```
+    pub fn borrow_as_file(&self) -> io::Result<FileRef<'_>> {
+        use std::os::windows::io::FromRawHandle;
+
+        if self.overlapped {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the pipe is opened for overlapped IO",
+            ));
+        }
+
+        // the FileRef never closes the handle
+        let file = unsafe { std::fs::File::from_raw_handle(self.handle.0 as _) };
+        Ok(FileRef::new(file))
+    }
+
     /// Tries to clone a instance to a new one.
     /// All cloned instances share the same underlaying data so
     /// Reading from one cloned pipe will affect an original pipe.
+    ///
+    /// The clone is always in a blocking mode, regardless of the mode of `self`.
     pub fn try_clone(&self) -> Result<Self, Error> {
-        clone_handle(self.handle).map_err(Into::into).map(Self::new)
+        let mut reader = clone_handle(self.handle).map(Self::new)?;
+        reader.keep_alive = self.keep_alive.clone();
+        reader.exit_watch = self.exit_watch.clone();
+        reader.overlapped = self.overlapped;
+        Ok(reader)
     }
 }
 
 impl Read for PipeReader {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        read_pipe(self.handle, buf, self.blocking)
+        read_pipe(self.pipe(), buf, self.blocking, self.exit_handle())
+    }
+
+    // ReadFileScatter can't be used with pipes,
+    // so a single read is done into a coalesced buffer which is then scattered.
+    // The mode is respected the same way as by a regular read.
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        let non_empty = bufs.iter().filter(|buf| !buf.is_empty()).count();
+        if non_empty <= 1 {
+            let buf = bufs.iter_mut().find(|buf| !buf.is_empty());
+            return self.read(buf.map_or(&mut [][..], |buf| &mut buf[..]));
+        }
+
+        let mut buf = vec![0; bufs.iter().map(|buf| buf.len()).sum()];
+        let n = self.read(&mut buf)?;
+
+        let mut data = &buf[..n];
+        for slice in bufs {
+            if data.is_empty() {
+                break;
+            }
+
+            let size = slice.len().min(data.len());
+            slice[..size].copy_from_slice(&data[..size]);
+            data = &data[size..];
+        }
+
+        Ok(n)
     }
 }
 
@@ -61,17 +323,36 @@ impl Drop for PipeReader {
     }
 }
 
+/// Converts the reader into a [std::fs::File] which owns the pipe HANDLE.
+///
+/// Beware of a pipe opened for overlapped IO, see [crate::ProcessOptions::overlapped_io].
 impl From<PipeReader> for std::fs::File {
     fn from(pipe: PipeReader) -> Self {
         use std::os::windows::io::FromRawHandle;
         // If we wouldn't wrap the reader in `ManuallyDrop`
         // the handle would be closed before the function
         // returned making the handle invalid.
-        let pipe = std::mem::ManuallyDrop::new(pipe);
+        let mut pipe = std::mem::ManuallyDrop::new(pipe);
+        drop(pipe.keep_alive.take());
+        drop(pipe.exit_watch.take());
         unsafe { std::fs::File::from_raw_handle(pipe.handle.0 as _) }
     }
 }
 
+/// Converts the reader into the pipe HANDLE it owns.
+///
+/// Beware of a pipe opened for overlapped IO, see [crate::ProcessOptions::overlapped_io].
+impl From<PipeReader> for std::os::windows::io::OwnedHandle {
+    fn from(pipe: PipeReader) -> Self {
+        use std::os::windows::io::FromRawHandle;
+        // see `From<PipeReader> for File`
+        let mut pipe = std::mem::ManuallyDrop::new(pipe);
+        drop(pipe.keep_alive.take());
+        drop(pipe.exit_watch.take());
+        unsafe { std::os::windows::io::OwnedHandle::from_raw_handle(pipe.handle.0 as _) }
+    }
+}
+
 impl fmt::Debug for PipeReader {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("PipeReader")
@@ -96,21 +377,173 @@ fn pipe_available_bytes(h: HANDLE) -> io::Result<u32> {
     Ok(bytes)
 }
 
-fn read_pipe(h: HANDLE, buf: &mut [u8], blocking: bool) -> io::Result<usize> {
+fn read_pipe(
+    h: Pipe,
+    buf: &mut [u8],
+    blocking: bool,
+    process: Option<HANDLE>,
+) -> io::Result<usize> {
+    if let (true, Some(process)) = (blocking, process) {
+        return read_pipe_until_exit(h, buf, process);
+    }
+
     if !blocking {
         // We could use SetNamedPipeHandleState but seems like it doesn't work sometimes?
         // Plus it changes all DUPed handles
 
-        let available = pipe_available_bytes(h)?;
-        if available == 0 {
-            return Err(io::Error::new(io::ErrorKind::WouldBlock, ""));
+        match pipe_available_bytes_or_eof(h.handle)? {
+            None => return Ok(0),
+            Some(0) => return Err(io::Error::new(io::ErrorKind::WouldBlock, "")),
+            Some(_) => {}
         }
     }
 
     read_from_pipe(h, buf)
 }
 
-fn read_from_pipe(h: HANDLE, buf: &mut [u8]) -> io::Result<usize> {
+fn unexpected_eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "failed to fill whole buffer")
+}
+
+fn read_pipe_timeout(h: Pipe, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+    const MAX_BACKOFF: Duration = Duration::from_millis(16);
+
+    let deadline = Instant::now() + timeout;
+    let mut backoff = Duration::from_millis(1);
+    loop {
+        match pipe_available_bytes_or_eof(h.handle)? {
+            None => return Ok(0),
+            Some(0) => {}
+            Some(_) => return read_from_pipe(h, buf),
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "no data was available in time",
+            ));
+        }
+
+        thread::sleep(backoff.min(deadline - now));
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+fn read_pipe_until_exit(h: Pipe, buf: &mut [u8], process: HANDLE) -> io::Result<usize> {
+    let mut exited_at: Option<Instant> = None;
+    loop {
+        match pipe_available_bytes_or_eof(h.handle)? {
+            None => return Ok(0),
+            Some(0) => {}
+            Some(_) => return read_from_pipe(h, buf),
+        }
+
+        match exited_at {
+            // the console may still flush the rest of the output after the exit
+            Some(time) if time.elapsed() >= EXIT_FLUSH_TIMEOUT => return Ok(0),
+            Some(_) => thread::sleep(Duration::from_millis(EXIT_POLL_INTERVAL_MILLIS as u64)),
+            None => {
+                let event = unsafe { WaitForSingleObject(process, EXIT_POLL_INTERVAL_MILLIS) };
+                if event == WAIT_OBJECT_0 {
+                    exited_at = Some(Instant::now());
+                }
+            }
+        }
+    }
+}
+
+fn read_pipe_until(
+    h: Pipe,
+    delim: u8,
+    buf: &mut Vec<u8>,
+    blocking: bool,
+    process: Option<HANDLE>,
+) -> io::Result<usize> {
+    let mut chunk = [0; 1024];
+    let mut read = 0;
+    loop {
+        let peeked = match peek_pipe(h.handle, &mut chunk) {
+            Ok(n) => n,
+            Err(err) if is_broken_pipe(&err) => return Ok(read),
+            Err(err) => return Err(err),
+        };
+        if peeked == 0 {
+            // nothing is available so we wait for a single byte
+            let mut byte = [0; 1];
+            let n = read_pipe(h, &mut byte, blocking, process)?;
+
+            if n == 0 {
+                return Ok(read);
+            }
+
+            buf.push(byte[0]);
+            read += 1;
+
+            if byte[0] == delim {
+                return Ok(read);
+            }
+
+            continue;
+        }
+
+        let (size, found) = match chunk[..peeked].iter().position(|&b| b == delim) {
+            Some(pos) => (pos + 1, true),
+            None => (peeked, false),
+        };
+
+        let start = buf.len();
+        buf.resize(start + size, 0);
+        let n = read_from_pipe(h, &mut buf[start..])?;
+        buf.truncate(start + n);
+        read += n;
+
+        if found && n == size {
+            return Ok(read);
+        }
+    }
+}
+
+fn peek_pipe(h: HANDLE, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+
+    unsafe {
+        PeekNamedPipe(
+            h,
+            Some(buf.as_mut_ptr() as _),
+            buf.len() as u32,
+            Some(&mut n),
+            None,
+            None,
+        )?;
+    }
+
+    Ok(n as usize)
+}
+
+// Returns `None` if the pipe is broken.
+fn pipe_available_bytes_or_eof(h: HANDLE) -> io::Result<Option<u32>> {
+    match pipe_available_bytes(h) {
+        Ok(n) => Ok(Some(n)),
+        Err(err) if is_broken_pipe(&err) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+// A broken pipe is reported as EOF, like a closed PTY on Unix.
+fn read_from_pipe(h: Pipe, buf: &mut [u8]) -> io::Result<usize> {
+    let result = match h.overlapped {
+        true => read_overlapped(h.handle, buf),
+        false => read_from_pipe_handle(h.handle, buf),
+    };
+
+    match result {
+        Err(err) if is_broken_pipe(&err) => Ok(0),
+        result => result,
+    }
+}
+
+fn read_from_pipe_handle(h: HANDLE, buf: &mut [u8]) -> io::Result<usize> {
     let mut n = 0;
 
     unsafe {
@@ -0,0 +1,96 @@
+// VtStripper removes VT sequences from a stream of bytes.
+//
+// The state is kept between the calls so a sequence can be split across reads.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct VtStripper {
+    state: State,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum State {
+    #[default]
+    Ground,
+    // after ESC
+    Escape,
+    // after ESC and an intermediate byte (e.g. `ESC ( B`)
+    EscapeIntermediate,
+    // inside `ESC [`
+    Csi,
+    // inside a string terminated by BEL or ST (OSC, DCS, SOS, PM, APC)
+    String,
+    // after ESC inside a string
+    StringEscape,
+}
+
+impl VtStripper {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    // Writes bytes which are not part of VT sequences to `out`.
+    pub(crate) fn strip(&mut self, input: &[u8], out: &mut Vec<u8>) {
+        for &b in input {
+            self.state = match (self.state, b) {
+                (State::Ground, 0x1B) => State::Escape,
+                (State::Ground, _) => {
+                    out.push(b);
+                    State::Ground
+                }
+                (State::Escape, b'[') => State::Csi,
+                (State::Escape, b']' | b'P' | b'X' | b'^' | b'_') => State::String,
+                (State::Escape, 0x20..=0x2F) => State::EscapeIntermediate,
+                (State::Escape, _) => State::Ground,
+                (State::EscapeIntermediate, 0x20..=0x2F) => State::EscapeIntermediate,
+                (State::EscapeIntermediate, _) => State::Ground,
+                // parameters and intermediate bytes
+                (State::Csi, 0x20..=0x3F) => State::Csi,
+                (State::Csi, _) => State::Ground,
+                (State::String, 0x07) => State::Ground,
+                (State::String, 0x1B) => State::StringEscape,
+                (State::String, _) => State::String,
+                (State::StringEscape, b'\\') => State::Ground,
+                (State::StringEscape, _) => State::String,
+            };
+        }
+    }
+}
+
+// Removes VT sequences from a complete output.
+pub(crate) fn strip_vt(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len());
+    VtStripper::new().strip(input, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_test() {
+        let tests: [(&[u8], &[u8]); 6] = [
+            (b"Hello World", b"Hello World"),
+            (b"\x1b[31mred\x1b[0m", b"red"),
+            (b"\x1b[?25l\x1b[2J\x1b[HHi\r\n", b"Hi\r\n"),
+            (b"\x1b]0;title\x07text", b"text"),
+            (b"\x1b]0;title\x1b\\text", b"text"),
+            (b"\x1b(Babc\x1b=", b"abc"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(strip_vt(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn split_sequence_test() {
+        let input = b"a\x1b[38;5;1mb\x1b]0;t\x07c";
+        for split in 0..input.len() {
+            let mut stripper = VtStripper::new();
+            let mut out = Vec::new();
+            stripper.strip(&input[..split], &mut out);
+            stripper.strip(&input[split..], &mut out);
+            assert_eq!(out, b"abc");
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    future::Future,
+    io::{self, Read, Write},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    task::JoinHandle,
+};
+
+use super::{PipeReader, PipeWriter};
+
+const CHUNK_SIZE: usize = 4096;
+
+/// AsyncPipeReader implements [tokio::io::AsyncRead] for a [PipeReader].
+///
+/// Reads are offloaded to the blocking thread pool of the runtime,
+/// so it must be used within a tokio runtime.
+/// The reader must be in a blocking mode.
+#[derive(Debug)]
+pub struct AsyncPipeReader {
+    state: ReadState,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+#[derive(Debug)]
+enum ReadState {
+    Idle(Option<PipeReader>),
+    Busy(JoinHandle<(PipeReader, io::Result<Vec<u8>>)>),
+}
+
+impl AsyncPipeReader {
+    /// Creates a new reader.
+    pub fn new(reader: PipeReader) -> Self {
+        Self {
+            state: ReadState::Idle(Some(reader)),
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl AsyncRead for AsyncPipeReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        loop {
+            if this.pos < this.buf.len() {
+                let n = buf.remaining().min(this.buf.len() - this.pos);
+                buf.put_slice(&this.buf[this.pos..this.pos + n]);
+                this.pos += n;
+                return Poll::Ready(Ok(()));
+            }
+
+            match &mut this.state {
+                ReadState::Idle(reader) => {
+                    let mut reader = reader.take().expect("a reader is lost");
+                    let size = buf.remaining().max(CHUNK_SIZE);
+                    this.state = ReadState::Busy(tokio::task::spawn_blocking(move || {
+                        let mut chunk = vec![0; size];
+                        let result = reader.read(&mut chunk).map(|n| {
+                            chunk.truncate(n);
+                            chunk
+                        });
+
+                        (reader, result)
+                    }));
+                }
+                ReadState::Busy(task) => {
+                    let (reader, result) = ready!(Pin::new(task).poll(cx))?;
+                    this.state = ReadState::Idle(Some(reader));
+
+                    let chunk = result?;
+                    if chunk.is_empty() {
+                        return Poll::Ready(Ok(()));
+                    }
+
+                    this.buf = chunk;
+                    this.pos = 0;
+                }
+            }
+        }
+    }
+}
+
+/// AsyncPipeWriter implements [tokio::io::AsyncWrite] for a [PipeWriter].
+///
+/// Writes are offloaded to the blocking thread pool of the runtime,
+/// so it must be used within a tokio runtime.
+/// A write is done in background,
+/// its error is returned by a next write or flush.
+#[derive(Debug)]
+pub struct AsyncPipeWriter {
+    state: WriteState,
+}
+
+#[derive(Debug)]
+enum WriteState {
+    Idle(Option<PipeWriter>),
+    Busy(JoinHandle<(PipeWriter, io::Result<()>)>),
+}
+
+impl AsyncPipeWriter {
+    /// Creates a new writer.
+    pub fn new(writer: PipeWriter) -> Self {
+        Self {
+            state: WriteState::Idle(Some(writer)),
+        }
+    }
+
+    // Waits for a write in progress to complete.
+    fn poll_complete(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<&mut PipeWriter>> {
+        if let WriteState::Busy(task) = &mut self.state {
+            let (writer, result) = ready!(Pin::new(task).poll(cx))?;
+            self.state = WriteState::Idle(Some(writer));
+            result?;
+        }
+
+        match &mut self.state {
+            WriteState::Idle(Some(writer)) => Poll::Ready(Ok(writer)),
+            _ => panic!("a writer is lost"),
+        }
+    }
+}
+
+impl AsyncWrite for AsyncPipeWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let _ = ready!(this.poll_complete(cx))?;
+
+        let mut writer = match &mut this.state {
+            WriteState::Idle(writer) => writer.take().expect("a writer is lost"),
+            WriteState::Busy(_) => unreachable!(),
+        };
+        let data = buf.to_vec();
+        this.state = WriteState::Busy(tokio::task::spawn_blocking(move || {
+            let result = writer.write_all(&data);
+            (writer, result)
+        }));
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let writer = ready!(self.poll_complete(cx))?;
+        Poll::Ready(writer.flush())
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
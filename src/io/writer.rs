@@ -1,7 +1,8 @@
 use std::{
     ffi::c_void,
     fmt,
-    io::{self, Write},
+    io::{self, IoSlice, Write},
+    os::windows::io::RawHandle,
 };
 
 use windows::Win32::{
@@ -9,11 +10,17 @@ use windows::Win32::{
     Storage::FileSystem::{FlushFileBuffers, WriteFile},
 };
 
-use crate::{error::Error, util::clone_handle};
+use super::overlapped::write_overlapped;
+use crate::{
+    error::Error,
+    util::{clone_handle, duplicate_handle_into},
+};
 
 /// PipeWriter implements [std::io::Write] interface for win32 pipe.
 pub struct PipeWriter {
     handle: HANDLE,
+    // the handle was opened with FILE_FLAG_OVERLAPPED
+    overlapped: bool,
 }
 
 impl PipeWriter {
@@ -21,18 +28,60 @@ impl PipeWriter {
     ///
     /// It owns a HANDLE.
     pub fn new(handle: HANDLE) -> Self {
-        Self { handle }
+        Self {
+            handle,
+            overlapped: false,
+        }
+    }
+
+    // The handle must be opened with FILE_FLAG_OVERLAPPED.
+    pub(crate) fn overlapped(mut self, on: bool) -> Self {
+        self.overlapped = on;
+        self
+    }
+
+    /// Duplicates the pipe HANDLE into a handle table of a `target` process.
+    ///
+    /// `target` is a process HANDLE which must have `PROCESS_DUP_HANDLE` access right.
+    /// The returned HANDLE is valid only in the target process and it's the target's responsibility to close it.
+    ///
+    /// The HANDLE is duplicated with `DUPLICATE_SAME_ACCESS` so it has the same access rights as the pipe,
+    /// and `DUPLICATE_CLOSE_SOURCE` is not used so the pipe remains usable.
+    pub fn duplicate_into(&self, target: HANDLE) -> Result<RawHandle, Error> {
+        let handle = duplicate_handle_into(self.handle, target)?;
+        Ok(handle.0 as RawHandle)
     }
 
     /// Tries to make a clone of PipeWriter.
     pub fn try_clone(&self) -> Result<Self, Error> {
-        clone_handle(self.handle).map_err(Into::into).map(Self::new)
+        let writer = clone_handle(self.handle).map(Self::new)?;
+        Ok(writer.overlapped(self.overlapped))
     }
 }
 
 impl Write for PipeWriter {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        write_to_pipe(self.handle, buf)
+        match self.overlapped {
+            true => write_overlapped(self.handle, buf),
+            false => write_to_pipe(self.handle, buf),
+        }
+    }
+
+    // WriteFileGather can't be used with pipes,
+    // so the slices are coalesced into a single write instead of a write per slice.
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        let non_empty = bufs.iter().filter(|buf| !buf.is_empty()).count();
+        if non_empty <= 1 {
+            let buf = bufs.iter().find(|buf| !buf.is_empty());
+            return self.write(buf.map_or(&[][..], |buf| &buf[..]));
+        }
+
+        let mut buf = Vec::with_capacity(bufs.iter().map(|buf| buf.len()).sum());
+        for slice in bufs {
+            buf.extend_from_slice(slice);
+        }
+
+        self.write(&buf)
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -48,6 +97,9 @@ impl Drop for PipeWriter {
     }
 }
 
+/// Converts the writer into a [std::fs::File] which owns the pipe HANDLE.
+///
+/// Beware of a pipe opened for overlapped IO, see [crate::ProcessOptions::overlapped_io].
 impl From<PipeWriter> for std::fs::File {
     fn from(pipe: PipeWriter) -> Self {
         use std::os::windows::io::FromRawHandle;
@@ -59,6 +111,18 @@ impl From<PipeWriter> for std::fs::File {
     }
 }
 
+/// Converts the writer into the pipe HANDLE it owns.
+///
+/// Beware of a pipe opened for overlapped IO, see [crate::ProcessOptions::overlapped_io].
+impl From<PipeWriter> for std::os::windows::io::OwnedHandle {
+    fn from(pipe: PipeWriter) -> Self {
+        use std::os::windows::io::FromRawHandle;
+        // see `From<PipeWriter> for File`
+        let pipe = std::mem::ManuallyDrop::new(pipe);
+        unsafe { std::os::windows::io::OwnedHandle::from_raw_handle(pipe.handle.0 as _) }
+    }
+}
+
 impl fmt::Debug for PipeWriter {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("PipeWriter")
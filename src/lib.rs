@@ -12,6 +12,17 @@
 //! assert!(String::from_utf8_lossy(&buf).contains("Hello World"));
 //! ```
 //!
+//! To collect the whole output of a process it's recommended to use [Process::run_to_completion],
+//! because a read after a process exit may block.
+//!
+//! ```ignore
+//! let mut proc = conpty::spawn("echo Hello World").unwrap();
+//! let (code, output) = proc.run_to_completion().unwrap();
+//!
+//! assert_eq!(code, 0);
+//! assert!(String::from_utf8_lossy(&output).contains("Hello World"));
+//! ```
+//!
 //! [ConPTY]: https://devblogs.microsoft.com/commandline/windows-command-line-introducing-the-windows-pseudo-console-conpty/
 
 #![warn(
@@ -31,28 +42,114 @@
     rust_2018_idioms
 )]
 
-use std::{
-    ffi::{OsStr, OsString},
-    process::Command,
-};
+use std::{ffi::OsStr, process::Command, time::Duration};
 
 use error::Error;
+use io::PipeReader;
 
+mod attributes;
+mod cmdline;
 pub mod console;
 pub mod error;
+pub mod input;
 pub mod io;
 
 mod process;
+mod quirks;
+mod resolve;
+mod screen;
+mod session;
+mod shell;
+mod status;
 mod util;
 
+pub use process::ExitInfo;
+pub use process::MemoryInfo;
+pub use process::Output;
 pub use process::Process;
 pub use process::ProcessOptions;
+pub use process::ProcessWaiter;
+pub use process::ResizeHandle;
+pub use process::ResizeWatcher;
+pub use quirks::Quirks;
+pub use session::PtySession;
+pub use shell::{default_shell, set_default_shell, CmdMode, Shell};
+pub use status::ExitStatus;
 
-/// Spawns a command using `cmd.exe`.
+/// Spawns a command using a default shell, which is `cmd.exe` unless changed by [set_default_shell].
+///
+/// It's the same as [spawn_with_shell] called with [default_shell].
 pub fn spawn(command: impl AsRef<OsStr>) -> Result<Process, Error> {
-    let mut cmd = OsString::new();
-    cmd.push("cmd /C ");
-    cmd.push(command);
+    spawn_with_shell(default_shell(), command)
+}
+
+/// Spawns a command using a given shell.
+///
+/// [Shell::None] runs the command directly.
+///
+/// ```ignore
+/// let proc = conpty::spawn_with_shell(conpty::Shell::PowerShell, "Get-ChildItem").unwrap();
+/// ```
+pub fn spawn_with_shell(shell: Shell, command: impl AsRef<OsStr>) -> Result<Process, Error> {
+    let cmd = shell.wrap(command.as_ref());
+    Process::spawn(Command::new(&cmd))
+}
 
+/// Spawns a command using `cmd.exe` in a given mode.
+///
+/// [CmdMode::RunAndKeepOpen] keeps the shell alive after the command is done,
+/// so the process can be used interactively.
+///
+/// ```ignore
+/// let proc = conpty::spawn_cmd("cd C:\\", conpty::CmdMode::RunAndKeepOpen).unwrap();
+/// assert!(proc.is_alive());
+/// ```
+pub fn spawn_cmd(command: impl AsRef<OsStr>, mode: CmdMode) -> Result<Process, Error> {
+    let cmd = mode.wrap(command.as_ref());
     Process::spawn(Command::new(&cmd))
 }
+
+/// Spawns a command using `cmd.exe` and returns only a reader of its output.
+///
+/// The input side is not exposed.
+/// The waiter owns the process, so the output can be read until the waiter is dropped.
+///
+/// ```ignore
+/// use std::io::prelude::*;
+///
+/// let (waiter, mut reader) = conpty::spawn_output("echo Hello World").unwrap();
+///
+/// let mut buf = [0; 1028];
+/// let n = reader.read(&mut buf).unwrap();
+/// assert!(String::from_utf8_lossy(&buf[..n]).contains("Hello World"));
+///
+/// assert_eq!(waiter.wait(None).unwrap(), 0);
+/// ```
+pub fn spawn_output(command: impl AsRef<OsStr>) -> Result<(ProcessWaiter, PipeReader), Error> {
+    let mut proc = spawn(command)?;
+    let reader = proc.output()?;
+    let waiter = proc.into_waiter()?;
+
+    Ok((waiter, reader))
+}
+
+/// Runs a command using a default shell with a deadline,
+/// returning its exit code and an output with VT sequences stripped.
+///
+/// If the command doesn't finish in time it's killed and [Error::Timeout] is returned.
+/// It's built on [Process::run_to_completion] so it doesn't hang on a read after the exit.
+///
+/// ```ignore
+/// use std::time::Duration;
+///
+/// let (code, output) = conpty::run_timeout("echo Hello World", Duration::from_secs(5)).unwrap();
+/// assert_eq!(code, 0);
+/// assert!(output.contains("Hello World"));
+/// ```
+pub fn run_timeout(command: impl AsRef<OsStr>, timeout: Duration) -> Result<(u32, String), Error> {
+    let mut proc = spawn(command)?;
+    let (code, output) = proc.run_until(Some(timeout))?;
+    let output = String::from_utf8_lossy(&io::strip_vt(&output)).into_owned();
+
+    Ok((code, output))
+}
@@ -3,34 +3,59 @@
 use std::{
     ffi::{c_void, OsStr, OsString},
     fmt,
+    io::{self, Read, Write},
     mem::size_of,
-    os::windows::prelude::OsStrExt,
+    os::windows::{
+        io::{AsRawHandle, BorrowedHandle, RawHandle},
+        prelude::OsStrExt,
+    },
+    path::{Path, PathBuf},
     process::Command,
-    ptr::{null, null_mut},
-    time::Duration,
+    ptr::{self, null},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver},
+        Arc, Mutex, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
 use windows::{
-    core::{self as win, HRESULT, PCWSTR, PWSTR},
+    core::{self as win, PCWSTR, PWSTR},
     Win32::{
-        Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0, WAIT_TIMEOUT},
+        Foundation::{
+            CloseHandle, ERROR_ACCESS_DENIED, ERROR_FILE_NOT_FOUND, ERROR_PATH_NOT_FOUND, FILETIME,
+            HANDLE, WAIT_OBJECT_0, WAIT_TIMEOUT,
+        },
         Storage::FileSystem::{
-            CreateFileW, FILE_ATTRIBUTE_NORMAL, FILE_GENERIC_READ, FILE_GENERIC_WRITE,
-            FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+            CreateFileW, FILE_ATTRIBUTE_NORMAL, FILE_FLAG_FIRST_PIPE_INSTANCE,
+            FILE_FLAG_OVERLAPPED, FILE_GENERIC_READ, FILE_GENERIC_WRITE, FILE_SHARE_MODE,
+            FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING, PIPE_ACCESS_INBOUND,
+            PIPE_ACCESS_OUTBOUND,
         },
         System::{
             Console::{
-                ClosePseudoConsole, CreatePseudoConsole, GetConsoleMode,
-                GetConsoleScreenBufferInfo, ResizePseudoConsole, SetConsoleMode, CONSOLE_MODE,
-                CONSOLE_SCREEN_BUFFER_INFO, COORD, ENABLE_ECHO_INPUT, ENABLE_LINE_INPUT,
+                ClosePseudoConsole, CreatePseudoConsole, GetConsoleMode, ResizePseudoConsole,
+                SetConsoleMode, CONSOLE_MODE, COORD, ENABLE_ECHO_INPUT, ENABLE_LINE_INPUT,
                 ENABLE_VIRTUAL_TERMINAL_PROCESSING, HPCON,
             },
-            Pipes::CreatePipe,
+            JobObjects::{
+                AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+                SetInformationJobObject, TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+                JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+            },
+            Pipes::{
+                CreateNamedPipeW, CreatePipe, PeekNamedPipe, PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_TYPE_BYTE, PIPE_WAIT,
+            },
+            ProcessStatus::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS},
             Threading::{
-                CreateProcessW, DeleteProcThreadAttributeList, GetExitCodeProcess, GetProcessId,
-                InitializeProcThreadAttributeList, TerminateProcess, UpdateProcThreadAttribute,
-                WaitForSingleObject, CREATE_UNICODE_ENVIRONMENT, EXTENDED_STARTUPINFO_PRESENT,
-                INFINITE, LPPROC_THREAD_ATTRIBUTE_LIST, PROCESS_INFORMATION, STARTF_USESTDHANDLES,
+                CreateProcessAsUserW, CreateProcessW, GetExitCodeProcess, GetProcessHandleCount,
+                GetProcessId, GetProcessTimes, ResumeThread, TerminateProcess, WaitForInputIdle,
+                WaitForSingleObject, CREATE_NEW_CONSOLE, CREATE_SUSPENDED,
+                CREATE_UNICODE_ENVIRONMENT, DETACHED_PROCESS, EXTENDED_STARTUPINFO_PRESENT,
+                INFINITE, PROCESS_CREATION_FLAGS, PROCESS_INFORMATION, STARTF_USESTDHANDLES,
                 STARTUPINFOEXW,
             },
         },
@@ -38,17 +63,76 @@ use windows::{
 };
 
 use crate::{
+    attributes::{AttributeList, AttributeSet},
+    cmdline::{build_argv_commandline, quote_arg, quote_program},
+    console::Console,
     error::Error,
-    io::{PipeReader, PipeWriter},
-    util::clone_handle,
+    input::{InputRecord, KeyEvent},
+    io::{PipeReader, PipeWriter, TextLines},
+    quirks::Quirks,
+    resolve::{find_program, program_name},
+    screen::Screen,
+    shell::Shell,
+    status::ExitStatus,
+    util::{
+        clone_handle, console_attached, disable_inheritance, enable_inheritance, is_broken_pipe,
+        read_hang_likely, screen_buffer_info, window_size, OwnedHandle,
+    },
 };
 
+#[cfg(feature = "tokio")]
+use crate::io::{AsyncPipeReader, AsyncPipeWriter};
+
+// An exit code of a process terminated by [Process::kill].
+const KILL_EXIT_CODE: u32 = 1;
+
+// An interval with which a size of the host console is checked by [ResizeWatcher].
+const HOST_RESIZE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// A key which is interpreted as Ctrl-Break when pressed with Ctrl.
+const VK_CANCEL: u16 = 0x03;
+const SCAN_CODE_CANCEL: u16 = 0x46;
+const LEFT_CTRL_PRESSED: u32 = 0x0008;
+// A Device Status Report query of a cursor position.
+const DSR_CURSOR_POSITION: &[u8] = b"\x1b[6n";
+// A time given to a process to answer the DSR query.
+const CURSOR_POSITION_TIMEOUT: Duration = Duration::from_secs(1);
+// A time the output is drained for after the exit, once nothing more comes.
+const OUTPUT_FLUSH_GRACE: Duration = Duration::from_millis(100);
+// Ctrl-Z and Enter, which a console turns into EOF for a line mode reader.
+const CONSOLE_EOF: &[u8] = b"\x1a\r";
+
 /// Options for spawning a new process inside of pseudo console.
 ///
 /// To be used for customizing console. E.g. its size.
 #[derive(Debug, Default)]
 pub struct ProcessOptions {
     console_size: Option<COORD>,
+    quirks: Quirks,
+    program_path: Option<PathBuf>,
+    shell: Option<Shell>,
+    keep_console_on_exit: bool,
+    kill_on_drop: bool,
+    use_job_object: bool,
+    resolve_program: bool,
+    robust_reads: Option<bool>,
+    enable_host_vt: Option<bool>,
+    inherit_size: Option<bool>,
+    headless: bool,
+    wait_connected: Option<Duration>,
+    attributes: AttributeSet,
+    path_prepend: Vec<PathBuf>,
+    path_append: Vec<PathBuf>,
+    envs: Vec<(OsString, OsString)>,
+    inherit_env: Option<bool>,
+    current_dir: Option<PathBuf>,
+    separate_stderr: bool,
+    overlapped_io: bool,
+    track_screen: bool,
+    creation_flags: u32,
+    user_token: Option<std::os::windows::io::OwnedHandle>,
+    pipe_buffer_size: Option<u32>,
+    stdin: Option<std::os::windows::io::OwnedHandle>,
 }
 
 impl ProcessOptions {
@@ -56,28 +140,411 @@ impl ProcessOptions {
     ///
     /// Uses options specified on `self`.
     pub fn spawn(&self, command: Command) -> Result<Process, Error> {
-        spawn_command(command, self.console_size)
+        spawn_command(command, self)
     }
 
     /// Specifies the size (x,y) of the new pseudo console window.
     ///
     /// if set to None, size is inherited from parent console or a
     /// default value is used.
+    ///
+    /// Prefer [Self::size] which takes columns and rows explicitly.
     pub fn set_console_size(&mut self, size_xy: Option<(i16, i16)>) -> &mut Self {
         let console_size = size_xy.map(|(x, y)| COORD { X: x, Y: y });
         self.console_size = console_size;
         self
     }
+
+    /// Specifies the size of the new pseudo console window in columns and rows.
+    ///
+    /// A console size is limited by `i16::MAX`, a larger value is clamped to it.
+    /// [Error::InvalidSize] is returned by [Self::spawn] if `columns` or `rows` is 0.
+    ///
+    /// By default the size is inherited from parent console or a default value is used.
+    pub fn size(&mut self, columns: u16, rows: u16) -> &mut Self {
+        self.console_size = Some(COORD {
+            X: i16::try_from(columns).unwrap_or(i16::MAX),
+            Y: i16::try_from(rows).unwrap_or(i16::MAX),
+        });
+        self
+    }
+
+    /// Specifies the size of the new pseudo console window to be the size of a `console` window.
+    ///
+    /// The size is read at the call, see [Console::window_size].
+    /// If it can't be read (e.g. stdout is redirected) the size is left unchanged.
+    pub fn inherit_from(&mut self, console: &Console) -> &mut Self {
+        if let Ok((columns, rows)) = console.window_size() {
+            let _ = self.size(columns, rows);
+        }
+
+        self
+    }
+
+    /// Spawns a process with a console of a given size without touching a parent console.
+    ///
+    /// By default VT processing is enabled on a parent console and its size is inherited,
+    /// which fails for a process without a console (e.g. a `#![windows_subsystem = "windows"]` binary or a service).
+    /// In headless mode a parent console is never used,
+    /// so [Process::vt_enabled] is always `false`.
+    /// The size is handled like in [Self::size].
+    pub fn headless(&mut self, columns: u16, rows: u16) -> &mut Self {
+        self.headless = true;
+        self.size(columns, rows)
+    }
+
+    /// Waits until the pseudo console is connected before [Self::spawn] returns.
+    ///
+    /// The console is considered connected once `conhost.exe` writes its first output
+    /// (it emits initial VT sequences when it attaches),
+    /// so the first write to [Process::input] is not lost.
+    /// The wait also ends if the process exits or the `timeout` elapses,
+    /// it's not an error if the console didn't connect in time.
+    ///
+    /// Nothing is read from the pipe, so the initial output is still available to readers.
+    pub fn wait_connected(&mut self, timeout: Duration) -> &mut Self {
+        self.wait_connected = Some(timeout);
+        self
+    }
+
+    /// Sets quirks which are passed to `CreatePseudoConsole`.
+    ///
+    /// By default no quirks are used.
+    pub fn quirks(&mut self, quirks: Quirks) -> &mut Self {
+        self.quirks = quirks;
+        self
+    }
+
+    /// Sets an explicit path of the program which will be executed.
+    ///
+    /// By default the program is parsed by `CreateProcessW` from the command line,
+    /// which is ambiguous for paths with spaces (e.g. `C:\Program Files\...`).
+    /// When the path is set it's passed as `lpApplicationName`,
+    /// and the program of the [Command] is quoted to become `argv[0]` of the command line.
+    pub fn program_path(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.program_path = Some(path.as_ref().to_owned());
+        self
+    }
+
+    /// Keeps the pseudo console open after the [Process] is dropped.
+    ///
+    /// By default the console is closed on drop,
+    /// which flushes the rest of the output and ends the pipe.
+    /// When set, the console is closed only when the [Process] and all of its output readers are dropped
+    /// or [Process::close_console] is called,
+    /// so the final output can be read after the process exit.
+    ///
+    /// Each open console holds a `conhost.exe` instance and its pipes,
+    /// and a read blocks instead of reaching EOF until the console is closed.
+    pub fn keep_console_on_exit(&mut self, on: bool) -> &mut Self {
+        self.keep_console_on_exit = on;
+        self
+    }
+
+    /// Terminates the process when the [Process] is dropped if it's still running.
+    ///
+    /// It's off by default, so the process keeps running after the [Process] is dropped
+    /// (unlike `tokio::process` which kills it by default).
+    /// The exit code of a killed process is the same as the one of [Process::kill].
+    pub fn kill_on_drop(&mut self, on: bool) -> &mut Self {
+        self.kill_on_drop = on;
+        self
+    }
+
+    /// Puts the process into a job object, so its whole process tree is terminated at once.
+    ///
+    /// [Process::exit] and [Process::kill] terminate all processes in the job,
+    /// including the ones spawned by the process (e.g. by `cmd /C`).
+    /// The job is created with `JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE`,
+    /// so the tree is also terminated once the [Process] is dropped.
+    ///
+    /// It's off by default.
+    pub fn use_job_object(&mut self, on: bool) -> &mut Self {
+        self.use_job_object = on;
+        self
+    }
+
+    /// Verifies that the program exists before a pseudo console is created.
+    ///
+    /// The program is looked up the same way `CreateProcessW` does it,
+    /// and [Error::ProgramNotFound] is returned if it doesn't exist.
+    /// It's off by default.
+    pub fn resolve_program(&mut self, on: bool) -> &mut Self {
+        self.resolve_program = on;
+        self
+    }
+
+    /// Connects stderr of the process to a separate pipe instead of the pseudo console.
+    ///
+    /// The pipe is available via [Process::stderr].
+    /// ConPTY merges stdout and stderr so it's the only way to tell them apart,
+    /// though the process may detect that its stderr is not a console and change its behaviour
+    /// (e.g. a colored output).
+    ///
+    /// It's off by default.
+    pub fn separate_stderr(&mut self, on: bool) -> &mut Self {
+        self.separate_stderr = on;
+        self
+    }
+
+    /// Opens our ends of the pseudo console pipes for overlapped IO (`FILE_FLAG_OVERLAPPED`).
+    ///
+    /// It makes [PipeReader::read_overlapped] available,
+    /// which can be used to integrate the output into an event loop without polling.
+    /// [PipeReader] and [PipeWriter] keep working as usual.
+    ///
+    /// A named pipe is used instead of an anonymous one, as the latter doesn't support overlapped IO.
+    /// Beware that such a pipe must be read and written with an `OVERLAPPED` structure.
+    /// A synchronous `ReadFile` or `WriteFile` on its HANDLE (e.g. through a [std::fs::File] made from it)
+    /// is not supported, std aborts the process if such a call doesn't complete right away.
+    ///
+    /// It's off by default.
+    pub fn overlapped_io(&mut self, on: bool) -> &mut Self {
+        self.overlapped_io = on;
+        self
+    }
+
+    /// Keeps a copy of the console screen, which is returned by [Process::scrollback].
+    ///
+    /// The copy is rebuilt from the VT output of the console,
+    /// which is relayed by a background thread the same way as by [Process::tee_output_to].
+    /// So the output must still be read, otherwise the thread (and so the copy) is blocked once the pipe is full.
+    ///
+    /// It's a best effort:
+    ///
+    /// - Only the visible screen is kept, lines scrolled out of it are lost.
+    /// - Colors and other attributes are ignored.
+    /// - A wide character (e.g. an emoji) takes a single cell.
+    /// - Sequences which are not used by ConPTY to render a screen are ignored.
+    ///
+    /// It's off by default.
+    pub fn track_screen(&mut self, on: bool) -> &mut Self {
+        self.track_screen = on;
+        self
+    }
+
+    /// Adds process creation flags (e.g. `CREATE_NO_WINDOW` or `CREATE_NEW_PROCESS_GROUP`).
+    ///
+    /// The flags are ORed with the ones which are set by the crate
+    /// (`EXTENDED_STARTUPINFO_PRESENT` and `CREATE_UNICODE_ENVIRONMENT`),
+    /// calling it several times accumulates the flags.
+    ///
+    /// `CREATE_NEW_CONSOLE` and `DETACHED_PROCESS` are incompatible with a pseudo console,
+    /// as the process wouldn't be attached to it,
+    /// [Error::InvalidCreationFlags] is returned by [Self::spawn] if any of them is set.
+    pub fn creation_flags(&mut self, flags: u32) -> &mut Self {
+        self.creation_flags |= flags;
+        self
+    }
+
+    /// Makes the pseudo console get its input from a given handle (e.g. a file), e.g. to replay a recorded input.
+    ///
+    /// A background thread reads the handle until EOF and writes everything to the input pipe,
+    /// as if it was written to [Process::input].
+    /// The pipe is left open after EOF, as the console is closed once its input is closed.
+    /// Beware that the console treats it as a terminal input,
+    /// e.g. a new line must be `\r` and VT sequences are interpreted.
+    ///
+    /// [Process::input] returns [Error::InputClosed] for such a process.
+    /// The handle is duplicated on each spawn, so the options can be dropped after it.
+    pub fn stdin_from(&mut self, handle: std::os::windows::io::OwnedHandle) -> &mut Self {
+        self.stdin = Some(handle);
+        self
+    }
+
+    /// Sets a buffer size of the pseudo console pipes in bytes.
+    ///
+    /// A larger buffer lets a process which produces a lot of output (e.g. dumps large logs)
+    /// write more before it's blocked, so the output is read in larger chunks.
+    /// The system may round the size or use it only as a hint.
+    ///
+    /// Named pipes are used, as anonymous pipes don't respect the size in practice.
+    /// By default anonymous pipes with a default buffer size are used.
+    pub fn pipe_buffer_size(&mut self, bytes: u32) -> &mut Self {
+        self.pipe_buffer_size = Some(bytes);
+        self
+    }
+
+    /// Spawns the process on behalf of a user represented by a token (`CreateProcessAsUserW`).
+    ///
+    /// The token must be a primary token (e.g. from `LogonUserW` or `DuplicateTokenEx`)
+    /// with `TOKEN_QUERY`, `TOKEN_DUPLICATE` and `TOKEN_ASSIGN_PRIMARY` access.
+    /// Unless it's the caller's token (or a restricted version of it),
+    /// the caller needs `SE_INCREASE_QUOTA_NAME` and usually `SE_ASSIGNPRIMARYTOKEN_NAME` privileges,
+    /// which a service running as `LocalSystem` has.
+    ///
+    /// The environment is built the same way as usual (not from the user's profile),
+    /// so set it explicitly if needed.
+    ///
+    /// By default the process is spawned by `CreateProcessW` under the caller's token.
+    pub fn as_user(&mut self, token: std::os::windows::io::OwnedHandle) -> &mut Self {
+        self.user_token = Some(token);
+        self
+    }
+
+    /// Sets whether the size of the console of the current process is used
+    /// when no size is set explicitly.
+    ///
+    /// With it turned off the console is `80x25` by default
+    /// and the console of the current process is never opened to get its size,
+    /// which is useful when it's redirected or shouldn't be touched.
+    /// It's on by default.
+    pub fn inherit_size(&mut self, on: bool) -> &mut Self {
+        self.inherit_size = Some(on);
+        self
+    }
+
+    /// Sets whether VT sequence processing is enabled on the console of the current process during spawn.
+    ///
+    /// The pseudo console output consists of VT sequences,
+    /// so by default `ENABLE_VIRTUAL_TERMINAL_PROCESSING` is set on the host console
+    /// for the output to be shown properly when it's written there.
+    /// Beware that it's a side effect on the whole current process which is never reverted.
+    ///
+    /// With it turned off spawning doesn't change the mode of the host console,
+    /// so [Process::vt_enabled] is `false`.
+    /// It's on by default.
+    pub fn enable_host_vt(&mut self, on: bool) -> &mut Self {
+        self.enable_host_vt = Some(on);
+        self
+    }
+
+    /// Makes blocking reads of [Process::output] return EOF after the process exit
+    /// when a debugger is attached or the process is run by `cargo-nextest`.
+    ///
+    /// A read normally gets EOF once all write ends of the pipe are closed.
+    /// A debugger or a test harness may spawn the current process with inheritable handles,
+    /// in which case the write end is kept open by someone else and a read hangs forever after the exit.
+    /// In such an environment the readers poll the pipe and the process instead of blocking on the pipe.
+    ///
+    /// It's on by default, it has no effect if neither a debugger nor `cargo-nextest` is detected.
+    pub fn robust_reads(&mut self, on: bool) -> &mut Self {
+        self.robust_reads = Some(on);
+        self
+    }
+
+    /// Sets a working directory of the process.
+    ///
+    /// It's used only if the [Command] doesn't set a directory itself.
+    /// [Error::CurrentDirNotFound] is returned by [Self::spawn] if the directory doesn't exist.
+    pub fn current_dir(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        self.current_dir = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Sets an environment variable of the process.
+    ///
+    /// The variables are applied on top of the ones set on the [Command],
+    /// so if the same variable is set on both the one from [ProcessOptions] wins.
+    pub fn env(&mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> &mut Self {
+        self.envs
+            .push((key.as_ref().to_owned(), value.as_ref().to_owned()));
+        self
+    }
+
+    /// Sets multiple environment variables of the process.
+    ///
+    /// See [Self::env].
+    pub fn envs<I, K, V>(&mut self, vars: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        for (key, value) in vars {
+            let _ = self.env(key, value);
+        }
+
+        self
+    }
+
+    /// Sets whether the process inherits the environment of the current process.
+    ///
+    /// When it's on, the variables set on the [Command] and [ProcessOptions]
+    /// are applied on top of the environment of the current process
+    /// (a variable removed by [Command::env_remove] is not inherited).
+    /// When it's off, only the variables which were set explicitly are passed,
+    /// so the environment is empty if none were set.
+    ///
+    /// By default the environment is inherited only if no variable was set explicitly,
+    /// otherwise only the explicit variables are passed.
+    pub fn inherit_parent_env(&mut self, on: bool) -> &mut Self {
+        self.inherit_env = Some(on);
+        self
+    }
+
+    /// Adds a directory to the beginning of `PATH` of the process.
+    ///
+    /// The directories are merged with `PATH` set on the [Command] or,
+    /// if it's not set there, with the `PATH` of the current process
+    /// (unless [Self::inherit_parent_env] is off).
+    /// The rest of the environment is passed as usual.
+    pub fn prepend_path(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        self.path_prepend.push(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Adds a directory to the end of `PATH` of the process.
+    ///
+    /// See [Self::prepend_path].
+    pub fn append_path(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        self.path_append.push(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Sets a shell which is used to run a command.
+    ///
+    /// The command line of the [Command] is passed to the shell.
+    /// By default a command is run directly,
+    /// the global [crate::default_shell] is used only by [crate::spawn].
+    pub fn shell(&mut self, shell: Shell) -> &mut Self {
+        self.shell = Some(shell);
+        self
+    }
 }
 
 /// The structure is resposible for interations with spawned process.
 /// It handles IO and other operations related to a spawned process.
+///
+/// [Process] is [Sync] and the pipes are [Send],
+/// so they can be borrowed by scoped threads.
+///
+/// ```ignore
+/// use std::io::{BufRead, BufReader};
+///
+/// let mut proc = conpty::spawn("echo Hello World").unwrap();
+/// let mut reader = BufReader::new(proc.output().unwrap());
+///
+/// std::thread::scope(|s| {
+///     let line = s.spawn(|| {
+///         let mut line = String::new();
+///         reader.read_line(&mut line).unwrap();
+///         line
+///     });
+///
+///     assert_eq!(proc.wait(None).unwrap(), 0);
+///     assert!(line.join().unwrap().contains("Hello World"));
+/// });
+/// ```
 pub struct Process {
     input: HANDLE,
     output: HANDLE,
+    stderr: Option<OwnedHandle>,
+    // a job the process is assigned to, closing it terminates the process tree
+    job: Option<OwnedHandle>,
     _proc: PROCESS_INFORMATION,
-    _proc_info: STARTUPINFOEXW,
-    _console: HPCON,
+    _attributes: AttributeList,
+    console: Arc<PseudoConsole>,
+    keep_console_on_exit: bool,
+    kill_on_drop: bool,
+    exit_aware_reads: bool,
+    overlapped_io: bool,
+    vt_enabled: bool,
+    exit_code: OnceLock<u32>,
+    // a thread which relays the output, see [Process::tee_output_to]
+    output_relay: Option<thread::JoinHandle<()>>,
+    // an EOF was sent by [Process::close_input]
+    input_closed: bool,
 }
 
 impl Process {
@@ -103,14 +570,404 @@ impl Process {
         ProcessOptions::default().spawn(command)
     }
 
+    /// Spawns a program with given arguments without any shell.
+    ///
+    /// The program and the arguments are quoted so the process gets exactly the same `argv`
+    /// (according to the rules of `CommandLineToArgvW`).
+    ///
+    /// ```ignore
+    /// use std::ffi::{OsStr, OsString};
+    /// use conpty::Process;
+    ///
+    /// let args = [OsString::from("-c"), OsString::from("print('Hello World')")];
+    /// let proc = Process::spawn_argv(OsStr::new("python"), &args).unwrap();
+    /// ```
+    pub fn spawn_argv(program: &OsStr, args: &[OsString]) -> Result<Self, Error> {
+        let commandline = build_argv_commandline(program, args);
+        Self::spawn(Command::new(commandline))
+    }
+
     /// Returns a process's pid.
     pub fn pid(&self) -> u32 {
         get_process_pid(self._proc.hProcess)
     }
 
+    /// Returns memory and handle usage of the process.
+    ///
+    /// The process must be alive, for an exited process the counters are not meaningful.
+    pub fn memory_info(&self) -> Result<MemoryInfo, Error> {
+        let mut counters = PROCESS_MEMORY_COUNTERS::default();
+        let size = size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+        unsafe { GetProcessMemoryInfo(self._proc.hProcess, &mut counters, size)? };
+
+        let mut handle_count = 0;
+        unsafe { GetProcessHandleCount(self._proc.hProcess, &mut handle_count)? };
+
+        Ok(MemoryInfo {
+            working_set: counters.WorkingSetSize,
+            peak_working_set: counters.PeakWorkingSetSize,
+            pagefile_usage: counters.PagefileUsage,
+            peak_pagefile_usage: counters.PeakPagefileUsage,
+            page_fault_count: counters.PageFaultCount,
+            handle_count,
+        })
+    }
+
+    /// Returns a process HANDLE, e.g. to be assigned to a job object.
+    ///
+    /// The HANDLE is owned by the [Process] and is closed on drop,
+    /// use [BorrowedHandle::try_clone_to_owned] to keep it longer.
+    pub fn process_handle(&self) -> BorrowedHandle<'_> {
+        unsafe { BorrowedHandle::borrow_raw(self._proc.hProcess.0 as RawHandle) }
+    }
+
     /// Waits before process exists.
     pub fn wait(&self, timeout_millis: Option<u32>) -> Result<u32, Error> {
-        wait_process(self._proc.hProcess, timeout_millis)
+        if let Some(code) = self.exit_code() {
+            return Ok(code);
+        }
+
+        self.observe_exit(wait_process(self._proc.hProcess, timeout_millis))
+    }
+
+    /// Checks whether the process has exited without blocking.
+    ///
+    /// Returns `Ok(Some(code))` if the process has exited and `Ok(None)` if it's still running.
+    /// It works like [std::process::Child::try_wait].
+    pub fn try_wait(&self) -> Result<Option<u32>, Error> {
+        if let Some(code) = self.exit_code() {
+            return Ok(Some(code));
+        }
+
+        // the exit code can't be trusted while the process is running as it's STILL_ACTIVE (259),
+        // so the process state is checked first
+        self.wait_timeout(Duration::ZERO)
+    }
+
+    /// Waits before process exists or the `timeout` is reached.
+    ///
+    /// Returns `Ok(Some(code))` if the process has exited and `Ok(None)` on timeout,
+    /// unlike [Self::wait] which returns [Error::Timeout].
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<Option<u32>, Error> {
+        match self.wait(Some(duration_to_wait_millis(timeout))) {
+            Ok(code) => Ok(Some(code)),
+            Err(Error::Timeout(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Waits before process exists, returning [std::process::ExitStatus].
+    ///
+    /// It's useful for a code which already handles statuses of [std::process::Child].
+    pub fn wait_status(&self) -> Result<std::process::ExitStatus, Error> {
+        let code = self.wait(None)?;
+        Ok(ExitStatus::new(code).into())
+    }
+
+    /// Waits before process exists or the `deadline` is reached.
+    ///
+    /// A deadline in the past results in an immediate check.
+    /// [Error::Timeout] is returned if the process is still running at the deadline.
+    pub fn wait_deadline(&self, deadline: Instant) -> Result<u32, Error> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        self.wait(Some(duration_to_wait_millis(remaining)))
+    }
+
+    /// Waits before process exists, calling `on_tick` every `interval`.
+    ///
+    /// The callback is called on the calling thread between the waits,
+    /// so it can be used to update a progress indicator.
+    ///
+    /// Returns an exit code of the process.
+    pub fn wait_with_progress(
+        &self,
+        interval: Duration,
+        on_tick: impl FnMut(),
+    ) -> Result<u32, Error> {
+        if let Some(code) = self.exit_code() {
+            return Ok(code);
+        }
+
+        self.observe_exit(wait_process_with_progress(
+            self._proc.hProcess,
+            interval,
+            on_tick,
+        ))
+    }
+
+    /// Waits until a GUI process finished its initialization and is waiting for user input
+    /// with no input pending (`WaitForInputIdle`).
+    ///
+    /// It's only meaningful for GUI processes, for a console process which doesn't have
+    /// a message queue it returns immediately (either `Ok` or an error, depending on the system).
+    /// [Error::Timeout] is returned if the process wasn't idle in time.
+    pub fn wait_input_idle(&self, timeout: Duration) -> Result<(), Error> {
+        match unsafe { WaitForInputIdle(self._proc.hProcess, duration_to_wait_millis(timeout)) } {
+            0 => Ok(()),
+            code if code == WAIT_TIMEOUT.0 => Err(Error::Timeout(timeout)),
+            _ => Err(Error::Win(win::Error::from_win32())),
+        }
+    }
+
+    /// Returns an exit code of the process if its exit was observed.
+    ///
+    /// It returns `None` until the exit has been observed by some wait call
+    /// (e.g. [Self::wait] or [Self::run_to_completion]), after that the code is cached.
+    /// It doesn't make any system calls.
+    pub fn exit_code(&self) -> Option<u32> {
+        self.exit_code.get().copied()
+    }
+
+    fn observe_exit(&self, result: Result<u32, Error>) -> Result<u32, Error> {
+        let code = result?;
+        Ok(*self.exit_code.get_or_init(|| code))
+    }
+
+    /// Runs the process to completion, returning its exit code and all of its output.
+    ///
+    /// It's the recommended way to collect an output of a process,
+    /// as it doesn't hang on a read after the process exit
+    /// (which may happen when some other handle keeps the pipe open, e.g. under a debugger or a test harness).
+    ///
+    /// The output is read in a non blocking mode on a separate thread while the process is waited,
+    /// after the exit the pipe is drained until EOF or until nothing comes for a short while,
+    /// as the console may flush the rest of the output after the exit.
+    pub fn run_to_completion(&mut self) -> Result<(u32, Vec<u8>), Error> {
+        self.run_until(None)
+    }
+
+    /// Waits for the process to exit and collects all of its output,
+    /// like [std::process::Child::wait_with_output].
+    ///
+    /// The output is drained on a separate thread while the process is waited,
+    /// so a process which fills the pipe doesn't block forever.
+    /// The pseudo console merges stdout and stderr so both are in [Output::stdout].
+    ///
+    /// ```ignore
+    /// let proc = conpty::spawn("echo Hello World").unwrap();
+    /// let output = proc.wait_with_output().unwrap();
+    ///
+    /// assert_eq!(output.status, 0);
+    /// assert!(String::from_utf8_lossy(&output.stdout).contains("Hello World"));
+    /// ```
+    pub fn wait_with_output(mut self) -> Result<Output, Error> {
+        let (status, stdout) = self.run_until(None)?;
+        Ok(Output { status, stdout })
+    }
+
+    /// Waits before process exists, returning its exit code along with timing info.
+    ///
+    /// The times are taken from the system (`GetProcessTimes`),
+    /// so the wall-clock time is measured between the creation and the exit of the process
+    /// regardless of when the method is called.
+    /// The CPU times don't include children of the process.
+    pub fn wait_with_times(&self) -> Result<ExitInfo, Error> {
+        let code = self.wait(None)?;
+
+        let mut creation = FILETIME::default();
+        let mut exit = FILETIME::default();
+        let mut kernel = FILETIME::default();
+        let mut user = FILETIME::default();
+        unsafe {
+            GetProcessTimes(
+                self._proc.hProcess,
+                &mut creation,
+                &mut exit,
+                &mut kernel,
+                &mut user,
+            )?
+        };
+
+        let wall = filetime_to_duration(exit).saturating_sub(filetime_to_duration(creation));
+
+        Ok(ExitInfo {
+            code,
+            wall,
+            kernel_time: filetime_to_duration(kernel),
+            user_time: filetime_to_duration(user),
+        })
+    }
+
+    // Runs the process like `run_to_completion`,
+    // but kills it and returns [Error::Timeout] once the timeout elapses.
+    pub(crate) fn run_until(&mut self, timeout: Option<Duration>) -> Result<(u32, Vec<u8>), Error> {
+        let mut reader = self.output()?;
+        reader.blocking(false);
+
+        let exited = Arc::new(AtomicBool::new(false));
+        let handle = thread::spawn({
+            let exited = exited.clone();
+            move || drain_pipe_until(&mut reader, &exited)
+        });
+
+        let code = match self.wait(timeout.map(duration_to_wait_millis)) {
+            Err(Error::Timeout(_)) => {
+                let _ = self.kill();
+                let _ = self.wait(None);
+                Err(Error::Timeout(timeout.unwrap_or_default()))
+            }
+            result => result,
+        };
+        exited.store(true, Ordering::SeqCst);
+
+        let output = handle
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("a reader thread panicked")));
+        let output = output.map_err(win::Error::from)?;
+        let code = code?;
+
+        Ok((code, output))
+    }
+
+    /// Returns an iterator over readable lines of the output.
+    ///
+    /// The output is decoded and VT sequences are stripped, see [TextLines].
+    /// The iterator ends after the process exit even if the pipe is kept open by someone else.
+    ///
+    /// ```ignore
+    /// let mut proc = conpty::spawn("echo Hello World").unwrap();
+    /// let mut lines = proc.text_lines().unwrap();
+    ///
+    /// assert!(lines.any(|line| line.unwrap() == "Hello World"));
+    /// ```
+    pub fn text_lines(&mut self) -> Result<TextLines, Error> {
+        self.output_lines(true)
+    }
+
+    /// Returns an iterator over lines of the output.
+    ///
+    /// The output is decoded and split on `\n`, a trailing `\r` is trimmed.
+    /// VT sequences are stripped if `strip_vt` is set, otherwise they're kept as is.
+    /// The iterator ends after the process exit even if the pipe is kept open by someone else.
+    ///
+    /// ```ignore
+    /// let mut proc = conpty::spawn("echo Hello World").unwrap();
+    /// for line in proc.output_lines(false).unwrap() {
+    ///     println!("{:?}", line.unwrap());
+    /// }
+    /// ```
+    pub fn output_lines(&mut self, strip_vt: bool) -> Result<TextLines, Error> {
+        let reader = self.output()?.exit_aware(self.exit_watch())?;
+        match strip_vt {
+            true => Ok(TextLines::new(reader)),
+            false => Ok(TextLines::with_vt(reader)),
+        }
+    }
+
+    /// Returns a channel which receives the output in chunks.
+    ///
+    /// The output is read by a separate thread, the channel is closed on EOF or after the process exit.
+    /// The channel is unbounded, so if the receiver is slower than the process
+    /// the output piles up in memory, use [Self::output_channel_bounded] to limit it.
+    ///
+    /// The thread notices that the receiver was dropped only when it sends the next chunk,
+    /// so until then it keeps waiting for the output (holding a clone of the pipe),
+    /// at the latest it ends once the process exits.
+    pub fn output_channel(&mut self) -> Result<Receiver<Vec<u8>>, Error> {
+        let reader = self.output()?.exit_aware(self.exit_watch())?;
+        let (tx, rx) = mpsc::channel();
+        let _ = thread::spawn(move || send_output(reader, |chunk| tx.send(chunk).is_ok()));
+
+        Ok(rx)
+    }
+
+    /// Returns a channel which receives the output in chunks, holding at most `bound` chunks.
+    ///
+    /// It works like [Self::output_channel],
+    /// but the reader thread stops reading until there's space in the channel,
+    /// so the process is blocked on a write once the pipe is full.
+    pub fn output_channel_bounded(&mut self, bound: usize) -> Result<Receiver<Vec<u8>>, Error> {
+        let reader = self.output()?.exit_aware(self.exit_watch())?;
+        let (tx, rx) = mpsc::sync_channel(bound);
+        let _ = thread::spawn(move || send_output(reader, |chunk| tx.send(chunk).is_ok()));
+
+        Ok(rx)
+    }
+
+    /// Records the raw output into a file at a given path, while it can still be read as usual.
+    ///
+    /// The file is created (or truncated), it gets the bytes exactly as they come from the console,
+    /// including VT sequences, so it can be replayed in a terminal later.
+    ///
+    /// The console output is handed to a background thread,
+    /// which writes each chunk to the file first and then forwards it into an internal pipe,
+    /// which readers returned by [Self::output] (and the methods built on top of it) read from.
+    /// So nothing is lost and the file is never behind what's been read.
+    /// The thread ends on EOF of the console output (or after the process exit),
+    /// then the readers get EOF once they read all the forwarded output.
+    ///
+    /// The internal pipe has a limited size, so if nobody reads the output
+    /// the thread (and so the recording) is blocked once the pipe is full, the same way as the process is without a tee.
+    /// After the [Process] is dropped and there are no readers left, the output is only recorded.
+    ///
+    /// Readers taken before the call still read the console output directly,
+    /// racing the thread, so it must be called before any reader is created.
+    pub fn tee_output_to(&mut self, path: &Path) -> Result<(), Error> {
+        let mut file = std::fs::File::create(path).map_err(win::Error::from)?;
+
+        self.relay_output(move |chunk| file.write_all(chunk).is_ok())
+    }
+
+    /// Returns the visible lines of the console screen, trailing blanks are trimmed.
+    ///
+    /// The screen is tracked only if [ProcessOptions::track_screen] is set, see its limitations;
+    /// otherwise [Error::ScreenNotTracked] is returned.
+    /// It reflects the output which has been relayed so far,
+    /// so read the output up to an expected point first (e.g. the last line of a TUI app).
+    pub fn scrollback(&self) -> Result<Vec<String>, Error> {
+        match &self.console.screen {
+            Some(screen) => Ok(screen.lock().unwrap().lines()),
+            None => Err(Error::ScreenNotTracked),
+        }
+    }
+
+    // Relays the console output through a background thread which hands each chunk to a sink first.
+    //
+    // The sink returns false to stop the relay.
+    fn relay_output(
+        &mut self,
+        mut sink: impl FnMut(&[u8]) -> bool + Send + 'static,
+    ) -> Result<(), Error> {
+        let (relay_reader, relay_writer) = match self.overlapped_io {
+            true => named_pipe(true, true, 0)?,
+            false => pipe()?,
+        };
+        let mut relay_writer = PipeWriter::new(relay_writer);
+
+        let source = PipeReader::new(clone_handle(self.output)?).overlapped(self.overlapped_io);
+        let source = match self.keep_console_on_exit {
+            true => source.keep_alive(self.console.clone()),
+            false => source,
+        };
+        let mut source = source.exit_aware(self.exit_watch())?;
+
+        unsafe {
+            let _ = CloseHandle(self.output);
+        }
+        self.output = relay_reader;
+
+        let relay = thread::spawn(move || {
+            let mut forward = true;
+            let mut buf = [0; 4096];
+            loop {
+                let n = match source.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => n,
+                };
+
+                if !sink(&buf[..n]) {
+                    break;
+                }
+
+                // the readers are gone, so just keep recording
+                if forward && relay_writer.write_all(&buf[..n]).is_err() {
+                    forward = false;
+                }
+            }
+        });
+        self.output_relay = Some(relay);
+
+        Ok(())
     }
 
     /// Is alive determines if a process is still running.
@@ -121,14 +978,341 @@ impl Process {
         is_process_alive(self._proc.hProcess)
     }
 
+    /// Returns a standalone waiter for the process.
+    ///
+    /// The waiter owns a duplicated process HANDLE,
+    /// so it can be moved to another thread while the [Process] is still used for IO and resize.
+    /// Its lifetime is independent of the [Process].
+    pub fn waiter(&self) -> Result<ProcessWaiter, Error> {
+        let handle = clone_handle(self._proc.hProcess)?;
+        Ok(ProcessWaiter {
+            handle,
+            _process: None,
+        })
+    }
+
+    /// Converts the process into a waiter.
+    ///
+    /// The waiter owns the process so the pseudo console is kept alive
+    /// until the waiter is dropped,
+    /// therefore the readers which were created before can still be used.
+    pub fn into_waiter(self) -> Result<ProcessWaiter, Error> {
+        let handle = clone_handle(self._proc.hProcess)?;
+        Ok(ProcessWaiter {
+            handle,
+            _process: Some(Box::new(self)),
+        })
+    }
+
+    /// Returns whether VT sequence processing was enabled on a parent console during spawn.
+    ///
+    /// It's `false` in case there's no console attached,
+    /// e.g. for a program with `#![windows_subsystem = "windows"]`,
+    /// or if it was turned off by [ProcessOptions::enable_host_vt].
+    /// In such case a host must interpret VT sequences on its own.
+    pub fn vt_enabled(&self) -> bool {
+        self.vt_enabled
+    }
+
+    /// Turns VT sequence processing on the console of the current process on or off.
+    ///
+    /// The pseudo console output consists of VT sequences,
+    /// so with the processing off the host console shows them as is,
+    /// which may be useful if the output is passed through to something else which interprets them.
+    /// It overrides the processing enabled during spawn (see [Self::vt_enabled]).
+    ///
+    /// The mode belongs to the host console, so it affects the whole current process,
+    /// other processes attached to the console, and it's left as is after the [Process] is dropped.
+    ///
+    /// [Error::NoConsoleAttached] is returned if the current process has no console.
+    pub fn set_vt_processing(&self, on: bool) -> Result<(), Error> {
+        if !console_attached() {
+            return Err(Error::NoConsoleAttached);
+        }
+
+        console_stdout_set_vt_processing(on)?;
+        Ok(())
+    }
+
+    /// Returns whether VT sequence processing is on for the console of the current process.
+    ///
+    /// See [Self::set_vt_processing].
+    /// [Error::NoConsoleAttached] is returned if the current process has no console.
+    pub fn vt_processing(&self) -> Result<bool, Error> {
+        if !console_attached() {
+            return Err(Error::NoConsoleAttached);
+        }
+
+        let stdout_h = OwnedHandle(stdout_handle()?);
+        let mode = console_mode(stdout_h.0)?;
+        Ok(mode.contains(ENABLE_VIRTUAL_TERMINAL_PROCESSING))
+    }
+
     /// Resizes virtual terminal.
+    ///
+    /// [Error::InvalidSize] is returned if `x` or `y` is not positive.
+    /// [Error::ConsoleClosed] is returned if the console is not alive,
+    /// see [Self::is_console_alive].
     pub fn resize(&mut self, x: i16, y: i16) -> Result<(), Error> {
-        resize_console(self._console, x, y)
+        self.console.resize(x, y)
+    }
+
+    /// Returns a size of virtual terminal as `(columns, rows)`.
+    ///
+    /// It's the size the console was created with or the last successfully applied one.
+    pub fn size(&self) -> (i16, i16) {
+        self.console.size()
+    }
+
+    /// Verifies whether the pseudo console is alive,
+    /// meaning it's not closed and the process attached to it is still running
+    /// (or the console is kept with [ProcessOptions::keep_console_on_exit]).
+    pub fn is_console_alive(&self) -> bool {
+        self.console.is_alive()
+    }
+
+    /// Returns a handle which can be used to resize the console from a different thread.
+    ///
+    /// Resizing via the handle doesn't require `&mut` access to the [Process],
+    /// so it can be done concurrently with IO (e.g. from a window event thread).
+    pub fn resize_handler(&self) -> ResizeHandle {
+        ResizeHandle {
+            console: self.console.clone(),
+        }
+    }
+
+    /// Resizes the pseudo console whenever the console of the current process is resized.
+    ///
+    /// The host console is polled on a separate thread,
+    /// which stops once the returned watcher is dropped or the pseudo console is closed,
+    /// see [Self::is_console_alive].
+    /// The pseudo console gets the size of the host console right away if they differ.
+    ///
+    /// An error is returned if the current process has no console.
+    pub fn track_host_resize(&mut self) -> Result<ResizeWatcher, Error> {
+        let host = OwnedHandle(stdout_handle()?);
+        // make sure the host console is usable
+        let _ = screen_buffer_info(host.0)?;
+
+        let console = self.console.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = thread::spawn({
+            let stop = stop.clone();
+            move || watch_host_size(&host, &console, &stop)
+        });
+
+        Ok(ResizeWatcher {
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Writes input records to the process.
+    ///
+    /// The records are encoded as VT sequences, see [crate::input] for the mapping.
+    /// [InputRecord::Resize] resizes the pseudo console.
+    pub fn write_input_records(&mut self, records: &[InputRecord]) -> Result<(), Error> {
+        let mut writer = self.input()?;
+        let mut buf = Vec::new();
+        for record in records {
+            match record {
+                InputRecord::Resize { columns, rows } => {
+                    write_input(&mut writer, &buf)?;
+                    buf.clear();
+
+                    self.resize(*columns, *rows)?;
+                }
+                record => buf.extend(record.to_vt().unwrap_or_default()),
+            }
+        }
+
+        write_input(&mut writer, &buf)
+    }
+
+    /// Sends Ctrl-C to the process.
+    ///
+    /// The `0x03` byte is written to the input,
+    /// the console translates it into `CTRL_C_EVENT` for the attached processes
+    /// if `ENABLE_PROCESSED_INPUT` is set on their console input (which is the default),
+    /// otherwise the process reads it as a regular input byte.
+    ///
+    /// [Error::InputClosed] is returned if the console is already closed.
+    pub fn send_ctrl_c(&mut self) -> Result<(), Error> {
+        self.send_control(b"\x03")
+    }
+
+    /// Sends Ctrl-Break to the process.
+    ///
+    /// There's no byte for Ctrl-Break so a `VK_CANCEL` key press with Ctrl is written
+    /// in `win32-input-mode` encoding, see [crate::input].
+    /// The console translates it into `CTRL_BREAK_EVENT`, which unlike Ctrl-C
+    /// is delivered regardless of the input mode.
+    ///
+    /// [Error::InputClosed] is returned if the console is already closed.
+    pub fn send_ctrl_break(&mut self) -> Result<(), Error> {
+        let mut key = KeyEvent {
+            key_down: true,
+            repeat_count: 1,
+            virtual_key_code: VK_CANCEL,
+            virtual_scan_code: SCAN_CODE_CANCEL,
+            unicode_char: 0,
+            control_key_state: LEFT_CTRL_PRESSED,
+        };
+
+        let mut buf = InputRecord::Key(key).to_vt().unwrap_or_default();
+        key.key_down = false;
+        buf.extend(InputRecord::Key(key).to_vt().unwrap_or_default());
+
+        self.send_control(&buf)
+    }
+
+    /// Reads and discards the output which is currently available, returning the number of bytes discarded.
+    ///
+    /// It never blocks, `PeekNamedPipe` tells how much can be read,
+    /// so the output which comes after the call is kept.
+    /// It's handy to skip a banner or a prompt before the next command is written.
+    ///
+    /// Once the output pipe is closed it's not an error, the count of discarded bytes is returned.
+    pub fn drain_output(&mut self) -> Result<usize, Error> {
+        let mut reader = PipeReader::new(clone_handle(self.output)?).overlapped(self.overlapped_io);
+
+        let mut drained = 0;
+        let mut buf = [0; 4096];
+        loop {
+            let available = match reader.bytes_available() {
+                Ok(0) => return Ok(drained),
+                Ok(n) => n.min(buf.len()),
+                Err(err) if is_broken_pipe(&err) => return Ok(drained),
+                Err(err) => return Err(Error::Win(win::Error::from(err))),
+            };
+
+            match reader.read(&mut buf[..available]) {
+                Ok(0) => return Ok(drained),
+                Ok(n) => drained += n,
+                Err(err) if is_broken_pipe(&err) => return Ok(drained),
+                Err(err) => return Err(Error::Win(win::Error::from(err))),
+            }
+        }
+    }
+
+    /// Returns a cursor position as a zero based (x, y) pair.
+    ///
+    /// ConPTY doesn't report the position directly,
+    /// so a Device Status Report query (`ESC[6n`) is written to the input
+    /// and a `ESC[row;colR` response is looked for in the output.
+    /// It works only for a VT-aware process which answers the query,
+    /// [Error::Timeout] is returned if there's no response within a second.
+    ///
+    /// The output which is read while waiting for the response is discarded,
+    /// so it's better to be called when the process is idle.
+    pub fn cursor_position(&self) -> Result<(i16, i16), Error> {
+        if self.console.is_closed() || self.input_closed {
+            return Err(Error::InputClosed);
+        }
+
+        let mut writer = PipeWriter::new(clone_handle(self.input)?).overlapped(self.overlapped_io);
+        let mut reader = PipeReader::new(clone_handle(self.output)?).overlapped(self.overlapped_io);
+        write_input(&mut writer, DSR_CURSOR_POSITION)?;
+
+        let deadline = Instant::now() + CURSOR_POSITION_TIMEOUT;
+        let mut output = Vec::new();
+        let mut buf = [0; 1024];
+        loop {
+            if let Some(position) = find_cursor_position_report(&output) {
+                return Ok(position);
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match reader.read_timeout(&mut buf, remaining) {
+                Ok(0) => return Err(Error::ConsoleClosed),
+                Ok(n) => output.extend_from_slice(&buf[..n]),
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+                    return Err(Error::Timeout(CURSOR_POSITION_TIMEOUT))
+                }
+                Err(err) if is_broken_pipe(&err) => return Err(Error::ConsoleClosed),
+                Err(err) => return Err(Error::Win(win::Error::from(err))),
+            }
+        }
+    }
+
+    fn send_control(&mut self, buf: &[u8]) -> Result<(), Error> {
+        if self.console.is_closed() {
+            return Err(Error::InputClosed);
+        }
+
+        let mut writer = self.input()?;
+        write_input(&mut writer, buf)
     }
 
     /// Termianates process with exit_code.
     pub fn exit(&mut self, code: u32) -> Result<(), Error> {
-        kill_process(self._proc.hProcess, code)
+        self.terminate(code)
+    }
+
+    /// Terminates the process with exit code 1.
+    ///
+    /// It's idempotent, killing a process which has already exited is not an error.
+    pub fn kill(&mut self) -> Result<(), Error> {
+        // children in a job may outlive the process
+        if !self.is_alive() && self.job.is_none() {
+            return Ok(());
+        }
+
+        match self.terminate(KILL_EXIT_CODE) {
+            // the process may exit between the check and the call
+            Err(_) if !self.is_alive() => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Sends Ctrl-C to the process and terminates it if it doesn't exit within `grace`.
+    ///
+    /// The process is terminated with exit code 1 like [Self::kill],
+    /// it's done right away if Ctrl-C can't be sent as the input is closed.
+    ///
+    /// Returns an exit code of the process.
+    pub fn shutdown(&mut self, grace: Duration) -> Result<u32, Error> {
+        if let Some(code) = self.exit_code() {
+            return Ok(code);
+        }
+
+        let interrupted = match self.send_ctrl_c() {
+            Ok(()) => true,
+            Err(Error::InputClosed) => false,
+            Err(err) => return Err(err),
+        };
+
+        if interrupted {
+            if let Some(code) = self.wait_timeout(grace)? {
+                return Ok(code);
+            }
+        }
+
+        self.kill()?;
+        self.wait(None)
+    }
+
+    fn terminate(&self, code: u32) -> Result<(), Error> {
+        match &self.job {
+            Some(job) => kill_job(job.0, code),
+            None => kill_process(self._proc.hProcess, code),
+        }
+    }
+
+    /// Makes sure the pipe handles of the process can't be inherited by child processes.
+    ///
+    /// A read of [Self::output] reaches EOF only when all write ends of the pipe are closed.
+    /// If a handle is inherited by some other process (e.g. spawned by [std::process::Command],
+    /// which inherits all inheritable handles) it keeps the pipe open for as long as the process lives,
+    /// so a read hangs after the exit.
+    ///
+    /// The handles are created non inheritable and the ones returned by [Self::input] and [Self::output]
+    /// are not inheritable either, so the call is only needed if the inheritance was changed
+    /// through the raw handles.
+    pub fn ensure_no_inherited_write_ends(&self) -> Result<(), Error> {
+        disable_inheritance(self.input)?;
+        disable_inheritance(self.output)?;
+        Ok(())
     }
 
     /// Sets echo mode for a session.
@@ -137,10 +1321,79 @@ impl Process {
     }
 
     /// Returns a pipe writer to conPTY.
+    ///
+    /// [Error::InputClosed] is returned once [Self::close_input] was called.
     pub fn input(&mut self) -> Result<PipeWriter, Error> {
+        if self.input_closed {
+            return Err(Error::InputClosed);
+        }
+
         // see [Self::output]
         let handle = clone_handle(self.input)?;
-        Ok(PipeWriter::new(handle))
+        Ok(PipeWriter::new(handle).overlapped(self.overlapped_io))
+    }
+
+    /// Writes all of `data` to the input of the process, returning the number of bytes written.
+    ///
+    /// Unlike writing to [Self::input] the stored pipe handle is used directly,
+    /// so nothing is duplicated per call.
+    ///
+    /// [Error::InputClosed] is returned once [Self::close_input] was called,
+    /// if the input is read from a handle given to [ProcessOptions::stdin_from]
+    /// or if the console is already closed.
+    pub fn write_input(&mut self, data: &[u8]) -> Result<usize, Error> {
+        if self.input_closed || self.console.is_closed() {
+            return Err(Error::InputClosed);
+        }
+
+        // the writer borrows the handle, it must not be closed on drop
+        let writer = PipeWriter::new(self.input).overlapped(self.overlapped_io);
+        let mut writer = std::mem::ManuallyDrop::new(writer);
+        write_input(&mut writer, data)?;
+
+        Ok(data.len())
+    }
+
+    /// Sends EOF to the process, so a reader of stdin (e.g. `sort`) finishes reading.
+    ///
+    /// A console input has no EOF on its own,
+    /// so `Ctrl-Z` and `Enter` are written the way a user ends the input in a console.
+    /// It works for a process which reads stdin in a line mode (the default),
+    /// `Ctrl-Z` must be at the beginning of a line, so the previous input must end with `\r`.
+    ///
+    /// The input pipe is kept open, as closing it makes `conhost.exe` close the whole pseudo console
+    /// rather than deliver EOF.
+    /// Consequent [Self::input] and [Self::write_input] calls return [Error::InputClosed],
+    /// writers returned by [Self::input] before are not affected.
+    ///
+    /// It's idempotent, closing an already closed input is not an error.
+    pub fn close_input(&mut self) -> Result<(), Error> {
+        if self.input_closed {
+            return Ok(());
+        }
+
+        let result = self.write_input(CONSOLE_EOF);
+        self.input_closed = true;
+
+        match result {
+            Ok(_) | Err(Error::InputClosed) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns a pipe reader of stderr of the process.
+    ///
+    /// It's available only if [ProcessOptions::separate_stderr] was set,
+    /// otherwise stderr is a part of [Self::output] and `None` is returned.
+    pub fn stderr(&mut self) -> Result<Option<PipeReader>, Error> {
+        let stderr = match &self.stderr {
+            Some(stderr) => stderr.0,
+            None => return Ok(None),
+        };
+
+        // see [Self::output]
+        let handle = clone_handle(stderr)?;
+        Ok(Some(PipeReader::new(handle)))
     }
 
     /// Returns a pipe reader from conPTY.
@@ -158,91 +1411,459 @@ impl Process {
         //
         // https://social.msdn.microsoft.com/Forums/windowsdesktop/en-US/1754715c-45b7-4d8c-ba56-a501ccaec12c/closehandle-amp-duplicatehandle?forum=windowsgeneraldevelopmentissues
         let handle = clone_handle(self.output)?;
-        Ok(PipeReader::new(handle))
+        let reader = PipeReader::new(handle).overlapped(self.overlapped_io);
+        let reader = match self.keep_console_on_exit {
+            true => reader.keep_alive(self.console.clone()),
+            false => reader,
+        };
+        let reader = match self.exit_aware_reads {
+            true => reader.exit_aware(self.exit_watch())?,
+            false => reader,
+        };
+
+        Ok(reader)
+    }
+
+    // Returns a handle which signals that no more output will come.
+    //
+    // It's the output relay thread if the output is teed,
+    // as the process may exit before the thread forwards the rest of the output.
+    fn exit_watch(&self) -> HANDLE {
+        match &self.output_relay {
+            Some(relay) => HANDLE(relay.as_raw_handle() as isize),
+            None => self._proc.hProcess,
+        }
+    }
+
+    /// Returns a pipe reader from conPTY which can be shared between threads.
+    ///
+    /// Unlike readers returned by several [Self::output] calls,
+    /// the threads share a single reader, so a blocking mode is the same for all of them
+    /// and a non blocking read (which peeks the pipe first) can't be raced by another thread.
+    ///
+    /// A blocking read holds the lock until there's data,
+    /// so consider to set a non blocking mode if the threads do something else.
+    pub fn output_shared(&mut self) -> Result<Arc<Mutex<PipeReader>>, Error> {
+        self.output().map(|reader| Arc::new(Mutex::new(reader)))
+    }
+
+    /// Returns an async pipe reader from conPTY.
+    ///
+    /// See [Self::output].
+    /// The reader must be used within a tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub fn output_async(&mut self) -> Result<AsyncPipeReader, Error> {
+        self.output().map(AsyncPipeReader::new)
+    }
+
+    /// Returns an async pipe writer to conPTY.
+    ///
+    /// See [Self::input].
+    /// The writer must be used within a tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub fn input_async(&mut self) -> Result<AsyncPipeWriter, Error> {
+        self.input().map(AsyncPipeWriter::new)
+    }
+
+    /// Closes the pseudo console.
+    ///
+    /// It terminates the console session,
+    /// the rest of the output is flushed and the readers reach the end of the pipe.
+    /// It's meant to be used along with [ProcessOptions::keep_console_on_exit].
+    pub fn close_console(&mut self) {
+        self.console.close();
     }
 }
 
 impl Drop for Process {
     fn drop(&mut self) {
-        unsafe {
-            ClosePseudoConsole(self._console);
+        if self.kill_on_drop && self.is_alive() {
+            let _ = self.terminate(KILL_EXIT_CODE);
+        }
 
+        if !self.keep_console_on_exit {
+            self.console.close();
+        }
+
+        unsafe {
             let _ = CloseHandle(self._proc.hProcess);
             let _ = CloseHandle(self._proc.hThread);
 
-            DeleteProcThreadAttributeList(self._proc_info.lpAttributeList);
-            let _: Box<u8> = Box::from_raw(self._proc_info.lpAttributeList.0 as *mut u8);
-
             let _ = CloseHandle(self.input);
             let _ = CloseHandle(self.output);
         }
     }
 }
 
-impl fmt::Debug for Process {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("PipeReader")
-            .field("pty_output", &(self.output.0))
-            .field("pty_output(ptr)", &(self.output.0 as *const c_void))
-            .field("pty_input", &(self.input.0))
-            .field("pty_input(ptr)", &(self.input.0 as *const c_void))
-            .finish_non_exhaustive()
+impl fmt::Debug for Process {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PipeReader")
+            .field("pty_output", &(self.output.0))
+            .field("pty_output(ptr)", &(self.output.0 as *const c_void))
+            .field("pty_input", &(self.input.0))
+            .field("pty_input(ptr)", &(self.input.0 as *const c_void))
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for Process {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // both calls are non blocking
+        let state = if self.is_alive() { "alive" } else { "exited" };
+        write!(f, "Process(pid={}, {})", self.pid(), state)
+    }
+}
+
+unsafe impl Send for Process {}
+unsafe impl Sync for Process {}
+
+/// ProcessWaiter can be used to wait for a spawned process from a different place than [Process].
+///
+/// It's created by [Process::waiter] or [Process::into_waiter].
+pub struct ProcessWaiter {
+    handle: HANDLE,
+    _process: Option<Box<Process>>,
+}
+
+impl ProcessWaiter {
+    /// Returns a process's pid.
+    pub fn pid(&self) -> u32 {
+        get_process_pid(self.handle)
+    }
+
+    /// Waits before process exists.
+    pub fn wait(&self, timeout_millis: Option<u32>) -> Result<u32, Error> {
+        wait_process(self.handle, timeout_millis)
+    }
+
+    /// Is alive determines if a process is still running.
+    pub fn is_alive(&self) -> bool {
+        is_process_alive(self.handle)
+    }
+}
+
+impl Drop for ProcessWaiter {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.handle);
+        }
+    }
+}
+
+impl fmt::Debug for ProcessWaiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProcessWaiter")
+            .field("handle", &(self.handle.0))
+            .field("handle(ptr)", &(self.handle.0 as *const c_void))
+            .finish()
+    }
+}
+
+unsafe impl Send for ProcessWaiter {}
+unsafe impl Sync for ProcessWaiter {}
+
+/// Output of a finished process.
+///
+/// It's returned by [Process::wait_with_output].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    /// An exit code of the process.
+    pub status: u32,
+    /// An output of the process, including VT sequences.
+    pub stdout: Vec<u8>,
+}
+
+/// Exit code and timing of a finished process.
+///
+/// It's returned by [Process::wait_with_times].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitInfo {
+    /// An exit code of the process.
+    pub code: u32,
+    /// A wall-clock time between the process creation and exit.
+    pub wall: Duration,
+    /// A time the process spent in kernel mode.
+    pub kernel_time: Duration,
+    /// A time the process spent in user mode.
+    pub user_time: Duration,
+}
+
+/// Memory and handle usage of a process.
+///
+/// It's returned by [Process::memory_info], the sizes are in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryInfo {
+    /// A current working set size.
+    pub working_set: usize,
+    /// A peak working set size.
+    pub peak_working_set: usize,
+    /// A current amount of private memory (commit charge).
+    pub pagefile_usage: usize,
+    /// A peak amount of private memory (commit charge).
+    pub peak_pagefile_usage: usize,
+    /// A number of page faults.
+    pub page_fault_count: u32,
+    /// A number of open handles.
+    pub handle_count: u32,
+}
+
+/// ResizeHandle can be used to resize a pseudo console of a [Process] from any thread.
+///
+/// It's created by [Process::resize_handler].
+///
+/// The handle doesn't keep the console alive
+/// (unless [ProcessOptions::keep_console_on_exit] is set),
+/// once the [Process] is dropped the console is closed
+/// and [ResizeHandle::resize] returns [Error::ConsoleClosed].
+/// The same error is returned after the process exit,
+/// unless the console is kept.
+/// Resizing is synchronized with the console closing,
+/// so a handle is never used after the console was released.
+#[derive(Debug, Clone)]
+pub struct ResizeHandle {
+    console: Arc<PseudoConsole>,
+}
+
+impl ResizeHandle {
+    /// Resizes virtual terminal.
+    pub fn resize(&self, x: i16, y: i16) -> Result<(), Error> {
+        self.console.resize(x, y)
+    }
+
+    /// Returns a size of virtual terminal.
+    ///
+    /// See [Process::size].
+    pub fn size(&self) -> (i16, i16) {
+        self.console.size()
+    }
+
+    /// Verifies whether the pseudo console is alive.
+    ///
+    /// See [Process::is_console_alive].
+    pub fn is_console_alive(&self) -> bool {
+        self.console.is_alive()
+    }
+}
+
+/// ResizeWatcher forwards resizes of the host console to a pseudo console.
+///
+/// It's created by [Process::track_host_resize].
+/// The watching is stopped on drop.
+#[derive(Debug)]
+pub struct ResizeWatcher {
+    stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ResizeWatcher {
+    /// Verifies whether the watcher is still running.
+    ///
+    /// It stops on its own once the pseudo console is closed.
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|t| !t.is_finished())
+    }
+}
+
+impl Drop for ResizeWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn watch_host_size(host: &OwnedHandle, console: &PseudoConsole, stop: &AtomicBool) {
+    let mut last = console.size();
+    while !stop.load(Ordering::SeqCst) && console.is_alive() {
+        if let Ok(info) = screen_buffer_info(host.0) {
+            let (columns, rows) = window_size(&info);
+            if (columns, rows) != last {
+                match console.resize(columns, rows) {
+                    Err(Error::ConsoleClosed) => return,
+                    // we don't retry a size which failed
+                    _ => last = (columns, rows),
+                }
+            }
+        }
+
+        thread::sleep(HOST_RESIZE_POLL_INTERVAL);
+    }
+}
+
+// PseudoConsole owns a HPCON which can be shared across threads.
+#[derive(Debug)]
+struct PseudoConsole {
+    handle: Mutex<Option<HPCON>>,
+    // a process attached to the console
+    process: OwnedHandle,
+    // the console outlives the process
+    keep_on_exit: bool,
+    // the last applied size
+    size: Mutex<COORD>,
+    // a copy of the screen, see [ProcessOptions::track_screen]
+    screen: Option<Arc<Mutex<Screen>>>,
+}
+
+impl PseudoConsole {
+    fn new(
+        handle: HPCON,
+        size: COORD,
+        process: OwnedHandle,
+        keep_on_exit: bool,
+        screen: Option<Arc<Mutex<Screen>>>,
+    ) -> Self {
+        Self {
+            handle: Mutex::new(Some(handle)),
+            process,
+            keep_on_exit,
+            size: Mutex::new(size),
+            screen,
+        }
+    }
+
+    fn size(&self) -> (i16, i16) {
+        let size = self.size.lock().unwrap();
+        (size.X, size.Y)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.handle.lock().unwrap().is_none()
+    }
+
+    fn is_alive(&self) -> bool {
+        let handle = self.handle.lock().unwrap();
+        self.is_open(&handle)
+    }
+
+    fn is_open(&self, handle: &Option<HPCON>) -> bool {
+        handle.is_some() && (self.keep_on_exit || is_process_alive(self.process.0))
+    }
+
+    fn resize(&self, x: i16, y: i16) -> Result<(), Error> {
+        if x <= 0 || y <= 0 {
+            return Err(Error::InvalidSize(x, y));
+        }
+
+        // we hold a lock so the console can't be closed while resizing
+        let handle = self.handle.lock().unwrap();
+        match *handle {
+            Some(console) if self.is_open(&handle) => {
+                resize_console(console, x, y)?;
+                *self.size.lock().unwrap() = COORD { X: x, Y: y };
+                if let Some(screen) = &self.screen {
+                    screen.lock().unwrap().resize(x, y);
+                }
+                Ok(())
+            }
+            _ => Err(Error::ConsoleClosed),
+        }
+    }
+
+    fn close(&self) {
+        let console = self.handle.lock().unwrap().take();
+        if let Some(console) = console {
+            unsafe { ClosePseudoConsole(console) };
+        }
+    }
+}
+
+impl Drop for PseudoConsole {
+    fn drop(&mut self) {
+        self.close();
     }
 }
 
-unsafe impl Send for Process {}
-unsafe impl Sync for Process {}
+// OwnedConsole closes a pseudo console on drop.
+//
+// It guards a console during spawn until it's handed to a [PseudoConsole].
+#[derive(Debug)]
+struct OwnedConsole(HPCON);
 
-fn enableVirtualTerminalSequenceProcessing() -> win::Result<()> {
-    let stdout_h = stdout_handle()?;
-    unsafe {
-        let mut mode = CONSOLE_MODE::default();
-        GetConsoleMode(stdout_h, &mut mode)?;
-        mode |= ENABLE_VIRTUAL_TERMINAL_PROCESSING; // DISABLE_NEWLINE_AUTO_RETURN
-        SetConsoleMode(stdout_h, mode)?;
+impl OwnedConsole {
+    fn into_raw(self) -> HPCON {
+        std::mem::ManuallyDrop::new(self).0
+    }
+}
 
-        CloseHandle(stdout_h)?;
+impl Drop for OwnedConsole {
+    fn drop(&mut self) {
+        unsafe { ClosePseudoConsole(self.0) };
     }
+}
 
-    Ok(())
+fn enableVirtualTerminalSequenceProcessing() -> win::Result<()> {
+    console_stdout_set_vt_processing(true)
+}
+
+fn console_stdout_set_vt_processing(on: bool) -> win::Result<()> {
+    let stdout_h = OwnedHandle(stdout_handle()?);
+    let mut mode = console_mode(stdout_h.0)?;
+    match on {
+        true => mode |= ENABLE_VIRTUAL_TERMINAL_PROCESSING, // DISABLE_NEWLINE_AUTO_RETURN
+        false => mode &= !ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+    };
+
+    unsafe { SetConsoleMode(stdout_h.0, mode) }
+}
+
+fn console_mode(handle: HANDLE) -> win::Result<CONSOLE_MODE> {
+    let mut mode = CONSOLE_MODE::default();
+    unsafe { GetConsoleMode(handle, &mut mode)? };
+    Ok(mode)
 }
 
-fn createPseudoConsole(size: COORD) -> win::Result<(HPCON, HANDLE, HANDLE)> {
-    let (pty_in, con_writer) = pipe()?;
-    let (con_reader, pty_out) = pipe()?;
+fn createPseudoConsole(
+    size: COORD,
+    flags: u32,
+    overlapped: bool,
+    buffer_size: Option<u32>,
+) -> win::Result<(OwnedConsole, OwnedHandle, OwnedHandle)> {
+    // anonymous pipes support neither overlapped IO nor a custom buffer size in practice
+    let named = overlapped || buffer_size.is_some();
+    let buffer_size = buffer_size.unwrap_or(0);
+    let (pty_in, con_writer) = match named {
+        true => named_pipe(false, overlapped, buffer_size)?,
+        false => pipe()?,
+    };
+    let (pty_in, con_writer) = (OwnedHandle(pty_in), OwnedHandle(con_writer));
+    let (con_reader, pty_out) = match named {
+        true => named_pipe(true, overlapped, buffer_size)?,
+        false => pipe()?,
+    };
+    let (con_reader, pty_out) = (OwnedHandle(con_reader), OwnedHandle(pty_out));
+
+    // Our ends of the pipes must not escape into any other process,
+    // otherwise a write end is kept open by it and a read never reaches EOF.
+    disable_inheritance(con_reader.0)?;
+    disable_inheritance(con_writer.0)?;
 
-    let console = unsafe { CreatePseudoConsole(size, pty_in, pty_out, 0)? };
+    let console = OwnedConsole(unsafe { CreatePseudoConsole(size, pty_in.0, pty_out.0, flags)? });
 
     // Note: We can close the handles to the PTY-end of the pipes here
     // because the handles are dup'ed into the ConHost and will be released
     // when the ConPTY is destroyed.
-    unsafe {
-        CloseHandle(pty_in)?;
-        CloseHandle(pty_out)?;
-    }
+    drop(pty_in);
+    drop(pty_out);
 
     Ok((console, con_reader, con_writer))
 }
 
 fn inhirentConsoleSize() -> win::Result<COORD> {
     let stdout_h = stdout_handle()?;
-    let mut info = CONSOLE_SCREEN_BUFFER_INFO::default();
-    unsafe {
-        GetConsoleScreenBufferInfo(stdout_h, &mut info)?;
-        CloseHandle(stdout_h)?;
-    };
+    let info = screen_buffer_info(stdout_h);
+    unsafe { CloseHandle(stdout_h)? };
+    let info = info?;
 
-    let mut size = COORD { X: 24, Y: 80 };
-    size.X = info.srWindow.Right - info.srWindow.Left + 1;
-    size.Y = info.srWindow.Bottom - info.srWindow.Top + 1;
+    let (columns, rows) = window_size(&info);
 
-    Ok(size)
+    Ok(COORD {
+        X: columns,
+        Y: rows,
+    })
 }
 
-// const PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE: usize = 22 | 0x0002_0000;
-const PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE: usize = 0x00020016;
-
-fn initializeStartupInfoAttachedToConPTY(hPC: &mut HPCON) -> win::Result<STARTUPINFOEXW> {
+fn initializeStartupInfo(attributes: &mut AttributeList, stderr: Option<HANDLE>) -> STARTUPINFOEXW {
     let mut siEx = STARTUPINFOEXW::default();
     siEx.StartupInfo.cb = size_of::<STARTUPINFOEXW>() as u32;
 
@@ -253,44 +1874,26 @@ fn initializeStartupInfoAttachedToConPTY(hPC: &mut HPCON) -> win::Result<STARTUP
     siEx.StartupInfo.hStdError.0 = 0;
     siEx.StartupInfo.dwFlags |= STARTF_USESTDHANDLES;
 
-    let mut size: usize = 0;
-    let res = unsafe {
-        InitializeProcThreadAttributeList(LPPROC_THREAD_ATTRIBUTE_LIST(null_mut()), 1, 0, &mut size)
-    };
-    if res.is_ok() /* according to the documentation this initial call must fail! */ || size == 0 {
-        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-initializeprocthreadattributelist#return-value
-        return Err(win::Error::new(
-            HRESULT::default(),
-            "failed initialize proc attribute list",
-        ));
+    if let Some(stderr) = stderr {
+        siEx.StartupInfo.hStdError = stderr;
     }
 
-    // SAFETY
-    // we leak the memory intentionally,
-    // it will be freed on DROP.
-    let lpAttributeList = vec![0u8; size].into_boxed_slice();
-    let lpAttributeList = Box::leak(lpAttributeList);
-
-    siEx.lpAttributeList = LPPROC_THREAD_ATTRIBUTE_LIST(lpAttributeList.as_mut_ptr() as _);
-
-    unsafe {
-        InitializeProcThreadAttributeList(siEx.lpAttributeList, 1, 0, &mut size)?;
-        UpdateProcThreadAttribute(
-            siEx.lpAttributeList,
-            0,
-            PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE,
-            Some(hPC.0 as _),
-            size_of::<HPCON>(),
-            None,
-            None,
-        )?;
-    }
+    siEx.lpAttributeList = attributes.as_ptr();
 
-    Ok(siEx)
+    siEx
 }
 
-fn execProc(command: Command, startup_info: STARTUPINFOEXW) -> win::Result<PROCESS_INFORMATION> {
-    let commandline = build_commandline(&command);
+fn execProc(
+    command: Command,
+    opts: &ProcessOptions,
+    startup_info: STARTUPINFOEXW,
+) -> Result<PROCESS_INFORMATION, Error> {
+    let program_path = opts.program_path.as_deref();
+    let commandline = build_process_commandline(&command, opts);
+    let program = || match program_path {
+        Some(path) => path.as_os_str().to_owned(),
+        None => program_name(&commandline),
+    };
     let mut commandline = convert_osstr_to_utf16(&commandline);
     let commandline = PWSTR(commandline.as_mut_ptr());
 
@@ -299,50 +1902,107 @@ fn execProc(command: Command, startup_info: STARTUPINFOEXW) -> win::Result<PROCE
     let current_dir = current_dir.as_ref().map_or(null(), |dir| dir.as_ptr());
     let current_dir = PCWSTR(current_dir);
 
-    let envs_list = || {
-        command
-            .get_envs()
-            .filter_map(|(key, value)| value.map(|value| (key, value)))
-    };
-    let envs = environment_block_unicode(envs_list());
-    let envs = if envs_list().next().is_some() {
-        Some(envs.as_ptr() as _)
-    } else {
-        None
-    };
+    let envs = build_environment(&command, opts).map(|envs| {
+        environment_block_unicode(envs.iter().map(|(k, v)| (k.as_os_str(), v.as_os_str())))
+    });
+    let envs = envs.as_ref().map(|envs| envs.as_ptr() as _);
+
+    let appname = program_path.map(|path| convert_osstr_to_utf16(path.as_os_str()));
+    let appname = PCWSTR(appname.as_ref().map_or(null(), |name| name.as_ptr()));
+    let mut dwflags = EXTENDED_STARTUPINFO_PRESENT | CREATE_UNICODE_ENVIRONMENT; // CREATE_UNICODE_ENVIRONMENT | CREATE_NEW_CONSOLE
+    dwflags |= PROCESS_CREATION_FLAGS(opts.creation_flags);
+    if opts.use_job_object {
+        // the process is resumed once it's assigned to a job,
+        // so it can't spawn a child outside of the job
+        dwflags |= CREATE_SUSPENDED;
+    }
 
-    let appname = PCWSTR(null_mut());
-    let dwflags = EXTENDED_STARTUPINFO_PRESENT | CREATE_UNICODE_ENVIRONMENT; // CREATE_UNICODE_ENVIRONMENT | CREATE_NEW_CONSOLE
+    // the inherited handles are limited by PROC_THREAD_ATTRIBUTE_HANDLE_LIST
+    let inherit_handles = opts.separate_stderr;
 
     let mut proc_info = PROCESS_INFORMATION::default();
-    unsafe {
-        CreateProcessW(
-            appname,
-            commandline,
-            None,
-            None,
-            false,
-            dwflags,
-            envs,
-            current_dir,
-            &startup_info.StartupInfo,
-            &mut proc_info,
-        )?
+    let result = match &opts.user_token {
+        Some(token) => unsafe {
+            CreateProcessAsUserW(
+                HANDLE(token.as_raw_handle() as isize),
+                appname,
+                commandline,
+                None,
+                None,
+                inherit_handles,
+                dwflags,
+                envs,
+                current_dir,
+                &startup_info.StartupInfo,
+                &mut proc_info,
+            )
+        },
+        None => unsafe {
+            CreateProcessW(
+                appname,
+                commandline,
+                None,
+                None,
+                inherit_handles,
+                dwflags,
+                envs,
+                current_dir,
+                &startup_info.StartupInfo,
+                &mut proc_info,
+            )
+        },
+    };
+
+    match result {
+        Ok(()) => Ok(proc_info),
+        Err(err) if err.code() == ERROR_FILE_NOT_FOUND.to_hresult() => {
+            Err(Error::ProgramNotFound(program()))
+        }
+        Err(err) if err.code() == ERROR_PATH_NOT_FOUND.to_hresult() => {
+            Err(Error::ProgramNotFound(program()))
+        }
+        Err(err) if err.code() == ERROR_ACCESS_DENIED.to_hresult() => {
+            Err(Error::PermissionDenied(program()))
+        }
+        Err(err) => Err(Error::Win(err)),
+    }
+}
+
+fn build_process_commandline(command: &Command, opts: &ProcessOptions) -> OsString {
+    let commandline = match opts.program_path {
+        Some(_) => build_commandline_quoted_program(command),
+        None => build_commandline(command),
     };
 
-    Ok(proc_info)
+    match opts.shell {
+        Some(shell) => shell.wrap(&commandline),
+        None => commandline,
+    }
 }
 
+// the program is taken as is, so it may contain a whole command line (e.g. `cmd /C echo`),
+// while the arguments are quoted
 fn build_commandline(command: &Command) -> OsString {
     let mut buf = OsString::new();
     buf.push(command.get_program());
+    push_args(&mut buf, command);
+
+    buf
+}
+
+// the program is expected to be a single argument in such case
+fn build_commandline_quoted_program(command: &Command) -> OsString {
+    let mut buf = quote_program(command.get_program());
+    push_args(&mut buf, command);
+
+    buf
+}
 
+fn push_args(buf: &mut OsString, command: &Command) {
     for arg in command.get_args() {
         buf.push(" ");
-        buf.push(arg);
+        buf.push(quote_arg(arg));
     }
-
-    buf
 }
 
 fn pipe() -> win::Result<(HANDLE, HANDLE)> {
@@ -353,6 +2013,82 @@ fn pipe() -> win::Result<(HANDLE, HANDLE)> {
     Ok((p_in, p_out))
 }
 
+// Returns a pipe like `pipe` does, but it's a named pipe which server end is ours.
+//
+// The server end is a reader if `server_reader` is set and a writer otherwise,
+// it's opened with FILE_FLAG_OVERLAPPED if `overlapped` is set.
+// A `buffer_size` of 0 means a default size.
+fn named_pipe(
+    server_reader: bool,
+    overlapped: bool,
+    buffer_size: u32,
+) -> win::Result<(HANDLE, HANDLE)> {
+    static PIPE_ID: AtomicUsize = AtomicUsize::new(0);
+
+    let id = PIPE_ID.fetch_add(1, Ordering::Relaxed);
+    let name = format!(r"\\.\pipe\conpty-{}-{}", std::process::id(), id);
+    let name = convert_osstr_to_utf16(OsStr::new(&name));
+    let name = PCWSTR(name.as_ptr());
+
+    let (server_access, client_access) = match server_reader {
+        true => (PIPE_ACCESS_INBOUND, FILE_GENERIC_WRITE),
+        false => (PIPE_ACCESS_OUTBOUND, FILE_GENERIC_READ),
+    };
+    let server_access = match overlapped {
+        true => server_access | FILE_FLAG_OVERLAPPED,
+        false => server_access,
+    };
+
+    let server = unsafe {
+        CreateNamedPipeW(
+            name,
+            server_access | FILE_FLAG_FIRST_PIPE_INSTANCE,
+            PIPE_TYPE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            1,
+            buffer_size,
+            buffer_size,
+            0,
+            None,
+        )
+    };
+    if server.is_invalid() {
+        return Err(win::Error::from_win32());
+    }
+
+    let client = unsafe {
+        CreateFileW(
+            name,
+            client_access.0,
+            FILE_SHARE_MODE(0),
+            None,
+            OPEN_EXISTING,
+            FILE_ATTRIBUTE_NORMAL,
+            HANDLE::default(),
+        )
+    };
+    let client = match client {
+        Ok(client) => client,
+        Err(err) => {
+            unsafe { CloseHandle(server)? };
+            return Err(err);
+        }
+    };
+
+    match server_reader {
+        true => Ok((server, client)),
+        false => Ok((client, server)),
+    }
+}
+
+// Returns a pipe for stderr of a process.
+//
+// The write end is not inheritable yet,
+// it's made inheritable only for the process creation.
+fn stderr_pipe() -> win::Result<(OwnedHandle, OwnedHandle)> {
+    let (reader, writer) = pipe()?;
+    Ok((OwnedHandle(reader), OwnedHandle(writer)))
+}
+
 fn stdout_handle() -> win::Result<HANDLE> {
     // we can't use `GetStdHandle(STD_OUTPUT_HANDLE)`
     // because it doesn't work when the IO is redirected
@@ -375,6 +2111,77 @@ fn stdout_handle() -> win::Result<HANDLE> {
     }
 }
 
+// Returns an environment of a process,
+// `None` means the environment of the current process is inherited.
+fn build_environment(
+    command: &Command,
+    opts: &ProcessOptions,
+) -> Option<Vec<(OsString, OsString)>> {
+    let explicit = command
+        .get_envs()
+        .map(|(key, value)| (key.to_owned(), value.map(OsStr::to_owned)))
+        .collect::<Vec<_>>();
+    let modify_path = !opts.path_prepend.is_empty() || !opts.path_append.is_empty();
+
+    // by default the environment is inherited only if no variable was set
+    let inherit = opts
+        .inherit_env
+        .unwrap_or_else(|| explicit.iter().all(|(_, value)| value.is_none()));
+
+    if inherit && opts.inherit_env.is_none() && !modify_path {
+        return None;
+    }
+
+    let is_path = |key: &OsStr| key.eq_ignore_ascii_case("PATH");
+    let path_removed = explicit
+        .iter()
+        .any(|(key, value)| is_path(key) && value.is_none());
+
+    let mut envs = match inherit {
+        true => std::env::vars_os().collect::<Vec<_>>(),
+        false => Vec::new(),
+    };
+
+    for (key, value) in explicit {
+        envs.retain(|(k, _)| !k.eq_ignore_ascii_case(&key));
+        if let Some(value) = value {
+            envs.push((key, value));
+        }
+    }
+
+    if modify_path {
+        let path = match envs.iter().position(|(key, _)| is_path(key)) {
+            Some(i) => Some(envs.remove(i).1),
+            None if path_removed || opts.inherit_env == Some(false) => None,
+            None => std::env::var_os("PATH"),
+        };
+
+        let path = merge_path(&opts.path_prepend, path, &opts.path_append);
+        envs.push((OsString::from("PATH"), path));
+    }
+
+    Some(envs)
+}
+
+fn merge_path(prepend: &[PathBuf], path: Option<OsString>, append: &[PathBuf]) -> OsString {
+    let dirs = prepend
+        .iter()
+        .map(|dir| dir.as_os_str())
+        .chain(path.as_deref().filter(|path| !path.is_empty()))
+        .chain(append.iter().map(|dir| dir.as_os_str()));
+
+    let mut merged = OsString::new();
+    for (i, dir) in dirs.enumerate() {
+        if i > 0 {
+            merged.push(";");
+        }
+
+        merged.push(dir);
+    }
+
+    merged
+}
+
 fn environment_block_unicode<'a>(
     env: impl IntoIterator<Item = (&'a OsStr, &'a OsStr)>,
 ) -> Vec<u16> {
@@ -406,6 +2213,10 @@ fn convert_osstr_to_utf16(s: &OsStr) -> Vec<u16> {
 
 fn console_stdout_set_echo(on: bool) -> Result<(), Error> {
     // todo: determine if this function is usefull and it works?
+    if !console_attached() {
+        return Err(Error::NoConsoleAttached);
+    }
+
     let stdout_h = stdout_handle()?;
 
     let mut mode = CONSOLE_MODE::default();
@@ -424,33 +2235,177 @@ fn console_stdout_set_echo(on: bool) -> Result<(), Error> {
     Ok(())
 }
 
-fn spawn_command(command: Command, size: Option<COORD>) -> Result<Process, Error> {
+fn spawn_command(mut command: Command, opts: &ProcessOptions) -> Result<Process, Error> {
+    // the options take precedence over the command
+    for (key, value) in &opts.envs {
+        let _ = command.env(key, value);
+    }
+
+    if let (None, Some(dir)) = (command.get_current_dir(), &opts.current_dir) {
+        let _ = command.current_dir(dir);
+    }
+
+    if let Some(size) = opts.console_size {
+        if size.X <= 0 || size.Y <= 0 {
+            return Err(Error::InvalidSize(size.X, size.Y));
+        }
+    }
+
+    let incompatible_flags = opts.creation_flags & (CREATE_NEW_CONSOLE.0 | DETACHED_PROCESS.0);
+    if incompatible_flags != 0 {
+        return Err(Error::InvalidCreationFlags(incompatible_flags));
+    }
+
+    if let Some(dir) = command.get_current_dir() {
+        if !dir.is_dir() {
+            return Err(Error::CurrentDirNotFound(dir.to_owned()));
+        }
+    }
+
+    if opts.resolve_program {
+        ensure_program_exists(&command, opts)?;
+    }
+
     // A Windows Subsystem process (i.e. one with WinMain) will not have a STDOUT, STDERR or STDIN,
     // unless it was specifically given one on launch.
     // The assumption is that since it is a windows program you are interacting with it via Windows.
     //
     // https://stackoverflow.com/questions/5115569/c-win32-api-getstdhandlestd-output-handle-is-invalid-very-perplexing
     //
-    // Because of this we are ignoring a error of VT sequence set and set a default size
-    //
-    // todo: It would be great to be able to identify whether a attribute #![windows_subsystem = "windows"] is set and ignore it only in such case
-    // But there's no way to do so?
-
-    let _ = enableVirtualTerminalSequenceProcessing();
-    let size = size
-        .or_else(|| inhirentConsoleSize().ok())
+    // Because of this we skip VT sequence setup and set a default size
+    // if there's no console attached, like in a headless mode.
+    let use_console = !opts.headless && console_attached();
+
+    let vt_enabled = use_console
+        && opts.enable_host_vt.unwrap_or(true)
+        && enableVirtualTerminalSequenceProcessing().is_ok();
+    let size = opts
+        .console_size
+        .or_else(|| match use_console && opts.inherit_size.unwrap_or(true) {
+            true => inhirentConsoleSize().ok(),
+            false => None,
+        })
         .unwrap_or(COORD { X: 80, Y: 25 });
 
-    let (mut console, output, input) = createPseudoConsole(size)?;
-    let startup_info = initializeStartupInfoAttachedToConPTY(&mut console)?;
-    let proc = execProc(command, startup_info)?;
-    Ok(Process {
-        input,
-        output,
-        _console: console,
+    // The console and the pipes are closed on any error below, until they're moved into the Process.
+    // They're declared in this order so the pipes are closed before the console,
+    // as closing a console may wait for its output to be read.
+    let (console, output, input) = createPseudoConsole(
+        size,
+        opts.quirks.bits(),
+        opts.overlapped_io,
+        opts.pipe_buffer_size,
+    )?;
+
+    // The console closes itself once its input reaches EOF,
+    // so a given stdin is pumped into the input pipe instead of being handed to the console.
+    let stdin = match &opts.stdin {
+        Some(stdin) => {
+            let source = std::fs::File::from(stdin.try_clone().map_err(win::Error::from)?);
+            let writer = PipeWriter::new(clone_handle(input.0)?).overlapped(opts.overlapped_io);
+            Some((source, writer))
+        }
+        None => None,
+    };
+    let mut attributes = opts.attributes.clone();
+    let _ = attributes.pseudo_console(console.0);
+
+    let stderr = match opts.separate_stderr {
+        true => Some(stderr_pipe()?),
+        false => None,
+    };
+    if let Some((_, writer)) = &stderr {
+        // only the write end is inherited by the process
+        let _ = attributes.handle_list(vec![writer.0]);
+    }
+
+    let mut attributes = AttributeList::new(attributes)?;
+    let stderr_writer = stderr.as_ref().map(|(_, writer)| writer.0);
+    let startup_info = initializeStartupInfo(&mut attributes, stderr_writer);
+    if let Some(writer) = stderr_writer {
+        // PROC_THREAD_ATTRIBUTE_HANDLE_LIST keeps the process from inheriting other handles,
+        // but any process spawned by someone else with inheritance inherits all inheritable handles,
+        // so the write end is inheritable only while the process is created
+        enable_inheritance(writer)?;
+    }
+    let proc = execProc(command, opts, startup_info);
+    // the process owns the write end now,
+    // so a read returns EOF once the process exits
+    let stderr = stderr.map(|(reader, _)| reader);
+    let proc = proc?;
+    let job = match opts.use_job_object {
+        true => Some(assign_job_object(&proc)?),
+        false => None,
+    };
+    let console_process = match clone_handle(proc.hProcess) {
+        Ok(handle) => OwnedHandle(handle),
+        Err(err) => {
+            abandon_process(&proc);
+            return Err(err.into());
+        }
+    };
+
+    if let Some(timeout) = opts.wait_connected {
+        wait_console_connected(output.0, proc.hProcess, timeout);
+    }
+
+    if let Some((mut source, mut writer)) = stdin {
+        // the writer is a clone, so the pipe stays open after EOF
+        let _ = thread::spawn(move || io::copy(&mut source, &mut writer));
+    }
+
+    let screen = opts
+        .track_screen
+        .then(|| Arc::new(Mutex::new(Screen::new(size.X, size.Y))));
+
+    let mut process = Process {
+        input: input.into_raw(),
+        output: output.into_raw(),
+        stderr,
+        job,
+        console: Arc::new(PseudoConsole::new(
+            console.into_raw(),
+            size,
+            console_process,
+            opts.keep_console_on_exit,
+            screen.clone(),
+        )),
+        keep_console_on_exit: opts.keep_console_on_exit,
+        kill_on_drop: opts.kill_on_drop,
+        exit_aware_reads: opts.robust_reads.unwrap_or(true) && read_hang_likely(),
+        overlapped_io: opts.overlapped_io,
         _proc: proc,
-        _proc_info: startup_info,
-    })
+        _attributes: attributes,
+        vt_enabled,
+        exit_code: OnceLock::new(),
+        output_relay: None,
+        input_closed: opts.stdin.is_some(),
+    };
+
+    if let Some(screen) = screen {
+        process.relay_output(move |chunk| {
+            screen.lock().unwrap().feed(chunk);
+            true
+        })?;
+    }
+
+    Ok(process)
+}
+
+fn ensure_program_exists(command: &Command, opts: &ProcessOptions) -> Result<(), Error> {
+    if let Some(path) = &opts.program_path {
+        return match path.is_file() {
+            true => Ok(()),
+            false => Err(Error::ProgramNotFound(path.clone().into_os_string())),
+        };
+    }
+
+    let commandline = build_process_commandline(command, opts);
+    let program = program_name(&commandline);
+    match find_program(&program, command.get_current_dir()) {
+        Some(_) => Ok(()),
+        None => Err(Error::ProgramNotFound(program)),
+    }
 }
 
 fn resize_console(console: HPCON, x: i16, y: i16) -> Result<(), Error> {
@@ -458,6 +2413,60 @@ fn resize_console(console: HPCON, x: i16, y: i16) -> Result<(), Error> {
     Ok(())
 }
 
+// Assigns a suspended process to a new job object and resumes it.
+//
+// The process is terminated if it can't be assigned.
+fn assign_job_object(proc: &PROCESS_INFORMATION) -> Result<OwnedHandle, Error> {
+    let job = create_job_object().and_then(|job| {
+        unsafe { AssignProcessToJobObject(job.0, proc.hProcess)? };
+        Ok(job)
+    });
+
+    let job = match job {
+        Ok(job) => job,
+        Err(err) => {
+            abandon_process(proc);
+            return Err(err.into());
+        }
+    };
+
+    let _ = unsafe { ResumeThread(proc.hThread) };
+
+    Ok(job)
+}
+
+// Kills a just spawned process which can't be handed out because the spawn failed later on,
+// so it's not left running unattended.
+fn abandon_process(proc: &PROCESS_INFORMATION) {
+    let _ = kill_process(proc.hProcess, KILL_EXIT_CODE);
+    unsafe {
+        let _ = CloseHandle(proc.hProcess);
+        let _ = CloseHandle(proc.hThread);
+    }
+}
+
+fn create_job_object() -> win::Result<OwnedHandle> {
+    let job = OwnedHandle(unsafe { CreateJobObjectW(None, PCWSTR::null())? });
+
+    let mut info = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+    info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+    unsafe {
+        SetInformationJobObject(
+            job.0,
+            JobObjectExtendedLimitInformation,
+            ptr::addr_of!(info) as *const c_void,
+            size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+        )?
+    };
+
+    Ok(job)
+}
+
+fn kill_job(job: HANDLE, code: u32) -> Result<(), Error> {
+    unsafe { TerminateJobObject(job, code)? };
+    Ok(())
+}
+
 fn get_process_pid(proc: HANDLE) -> u32 {
     unsafe { GetProcessId(proc) }
 }
@@ -467,19 +2476,39 @@ fn kill_process(proc: HANDLE, code: u32) -> Result<(), Error> {
     Ok(())
 }
 
+fn wait_console_connected(output: HANDLE, proc: HANDLE, timeout: Duration) {
+    const INTERVAL: Duration = Duration::from_millis(5);
+
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        let mut available = 0;
+        let peeked = unsafe { PeekNamedPipe(output, None, 0, None, Some(&mut available), None) };
+        if peeked.is_err() || available > 0 || !is_process_alive(proc) {
+            return;
+        }
+
+        thread::sleep(INTERVAL);
+    }
+}
+
 fn is_process_alive(proc: HANDLE) -> bool {
     // https://stackoverflow.com/questions/1591342/c-how-to-determine-if-a-windows-process-is-running/5303889
     unsafe { WaitForSingleObject(proc, 0) == WAIT_TIMEOUT }
 }
 
+// Converts a timeout to milliseconds of a wait call,
+// a too long one is clamped below INFINITE so it's still a finite wait.
+fn duration_to_wait_millis(timeout: Duration) -> u32 {
+    u32::try_from(timeout.as_millis()).unwrap_or(INFINITE - 1)
+}
+
 fn wait_process(proc: HANDLE, timeout_millis: Option<u32>) -> Result<u32, Error> {
     match timeout_millis {
-        Some(timeout) => {
-            let result = unsafe { WaitForSingleObject(proc, timeout) };
-            if result == WAIT_TIMEOUT {
-                return Err(Error::Timeout(Duration::from_millis(timeout as u64)));
-            }
-        }
+        Some(timeout) => match unsafe { WaitForSingleObject(proc, timeout) } {
+            WAIT_OBJECT_0 => {}
+            WAIT_TIMEOUT => return Err(Error::Timeout(Duration::from_millis(timeout as u64))),
+            event_id => return Err(Error::WaitFailed(event_id)),
+        },
         None => match unsafe { WaitForSingleObject(proc, INFINITE) } {
             WAIT_OBJECT_0 => {}
             event_id => return Err(Error::WaitFailed(event_id)),
@@ -494,10 +2523,172 @@ fn wait_process(proc: HANDLE, timeout_millis: Option<u32>) -> Result<u32, Error>
     Ok(code)
 }
 
+fn wait_process_with_progress(
+    proc: HANDLE,
+    interval: Duration,
+    mut on_tick: impl FnMut(),
+) -> Result<u32, Error> {
+    let interval = duration_to_wait_millis(interval);
+    loop {
+        match wait_process(proc, Some(interval)) {
+            Err(Error::Timeout(_)) => on_tick(),
+            result => return result,
+        }
+    }
+}
+
+fn write_input(writer: &mut PipeWriter, buf: &[u8]) -> Result<(), Error> {
+    if buf.is_empty() {
+        return Ok(());
+    }
+
+    writer
+        .write_all(buf)
+        .and_then(|_| writer.flush())
+        .map_err(|err| match is_broken_pipe(&err) {
+            true => Error::InputClosed,
+            false => Error::Win(win::Error::from(err)),
+        })
+}
+
+// Finds a cursor position report (`ESC[row;colR`), returning a zero based (x, y) pair.
+fn find_cursor_position_report(buf: &[u8]) -> Option<(i16, i16)> {
+    let mut start = 0;
+    while let Some(pos) = buf[start..].windows(2).position(|w| w == b"\x1b[") {
+        start += pos + 2;
+        if let Some(position) = parse_cursor_position_report(&buf[start..]) {
+            return Some(position);
+        }
+    }
+
+    None
+}
+
+// Parses `row;colR` which follows CSI.
+fn parse_cursor_position_report(buf: &[u8]) -> Option<(i16, i16)> {
+    let end = buf.iter().position(|&b| b == b'R')?;
+    let params = &buf[..end];
+    if !params.iter().all(|&b| b.is_ascii_digit() || b == b';') {
+        return None;
+    }
+
+    let params = std::str::from_utf8(params).ok()?;
+    let (row, col) = params.split_once(';')?;
+    let row = row.parse::<i16>().ok()?;
+    let col = col.parse::<i16>().ok()?;
+    if row < 1 || col < 1 {
+        return None;
+    }
+
+    Some((col - 1, row - 1))
+}
+
+// FILETIME is a number of 100-nanosecond intervals.
+fn filetime_to_duration(time: FILETIME) -> Duration {
+    let intervals = (u64::from(time.dwHighDateTime) << 32) | u64::from(time.dwLowDateTime);
+    Duration::from_nanos(intervals.saturating_mul(100))
+}
+
+// reads a blocking reader until EOF or an error and passes chunks to `send`,
+// it stops once `send` returns false.
+fn send_output(mut reader: PipeReader, mut send: impl FnMut(Vec<u8>) -> bool) {
+    let mut buf = [0; 4096];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => {
+                if !send(buf[..n].to_vec()) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+// reads everything available from a non blocking reader,
+// once the flag is set the reader is drained until EOF
+// or until nothing comes within `OUTPUT_FLUSH_GRACE`, as conhost may flush the output after the exit.
+fn drain_pipe_until(reader: &mut PipeReader, stop: &AtomicBool) -> io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    let mut buf = [0; 4096];
+    let mut idle_since = None;
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(output),
+            Ok(n) => {
+                output.extend_from_slice(&buf[..n]);
+                idle_since = None;
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                if stop.load(Ordering::SeqCst) {
+                    let idle_since = *idle_since.get_or_insert_with(Instant::now);
+                    if idle_since.elapsed() >= OUTPUT_FLUSH_GRACE {
+                        return Ok(output);
+                    }
+                }
+
+                thread::sleep(Duration::from_millis(10));
+            }
+            Err(err) if is_broken_pipe(&err) => return Ok(output),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn filetime_to_duration_test() {
+        let time = FILETIME {
+            dwLowDateTime: 15,
+            dwHighDateTime: 1,
+        };
+        let expected = Duration::from_nanos(((1 << 32) + 15) * 100);
+        assert_eq!(filetime_to_duration(time), expected);
+    }
+
+    #[test]
+    fn find_cursor_position_report_test() {
+        // an output and an expected position
+        type Case = (&'static [u8], Option<(i16, i16)>);
+
+        let tests: [Case; 7] = [
+            (b"\x1b[5;10R", Some((9, 4))),
+            (b"Hello\x1b[2J\x1b[1;1R World", Some((0, 0))),
+            (b"\x1b[?25l\x1b[12;3R", Some((2, 11))),
+            (b"\x1b[5;10", None),
+            (b"\x1b[0;10R", None),
+            (b"\x1b[5R", None),
+            (b"Hello World", None),
+        ];
+
+        for (buf, expected) in tests {
+            assert_eq!(
+                find_cursor_position_report(buf),
+                expected,
+                "{:?}",
+                String::from_utf8_lossy(buf)
+            );
+        }
+    }
+
+    #[test]
+    fn merge_path_test() {
+        let prepend = [PathBuf::from(r"C:\a"), PathBuf::from(r"C:\b")];
+        let append = [PathBuf::from(r"C:\z")];
+
+        let path = merge_path(&prepend, Some(OsString::from(r"C:\x;C:\y")), &append);
+        assert_eq!(path, r"C:\a;C:\b;C:\x;C:\y;C:\z");
+
+        let path = merge_path(&prepend, None, &[]);
+        assert_eq!(path, r"C:\a;C:\b");
+
+        let path = merge_path(&[], Some(OsString::new()), &append);
+        assert_eq!(path, r"C:\z");
+    }
+
     #[test]
     fn env_block_test() {
         let tests = [
@@ -520,6 +2711,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn commandline_test() {
+        let tests = [
+            (vec!["hello world"], r#"prog "hello world""#),
+            (vec![r#"a"b"#], r#"prog a\"b"#),
+            (vec![r"a\b"], r"prog a\b"),
+            (vec![r"a\ "], r#"prog "a\ ""#),
+            (vec![r"a b\"], r#"prog "a b\\""#),
+            (vec![""], r#"prog """#),
+            (vec!["/C", "exit", "5"], "prog /C exit 5"),
+        ];
+
+        for (args, expected) in tests {
+            let mut cmd = Command::new("prog");
+            let _ = cmd.args(&args);
+            assert_eq!(build_commandline(&cmd), expected, "{:?}", args);
+        }
+
+        // the program is kept as is
+        let cmd = Command::new("cmd /C echo Hello World");
+        assert_eq!(build_commandline(&cmd), "cmd /C echo Hello World");
+    }
+
+    #[test]
+    fn commandline_quoted_program_test() {
+        let mut cmd = Command::new(r"C:\Program Files\app.exe");
+        let _ = cmd.args(["/C", "echo"]);
+        assert_eq!(
+            build_commandline_quoted_program(&cmd),
+            r#""C:\Program Files\app.exe" /C echo"#
+        );
+
+        let cmd = Command::new("cmd");
+        assert_eq!(build_commandline_quoted_program(&cmd), "cmd");
+    }
+
+    #[test]
+    fn duration_to_wait_millis_test() {
+        assert_eq!(duration_to_wait_millis(Duration::ZERO), 0);
+        assert_eq!(duration_to_wait_millis(Duration::from_secs(2)), 2000);
+        assert_eq!(duration_to_wait_millis(Duration::MAX), INFINITE - 1);
+    }
+
     fn str_to_utf16(s: impl AsRef<str>) -> Vec<u16> {
         s.as_ref().encode_utf16().collect()
     }
@@ -0,0 +1,147 @@
+//! Module contains [Quirks] which are passed to `CreatePseudoConsole`.
+
+use std::{fmt, ops};
+
+/// Quirks is a set of flags which tweaks known behaviours of a pseudo console.
+///
+/// They are passed to `CreatePseudoConsole` as its `dwFlags` argument.
+/// Flags which are not recognized by an older Windows build
+/// may cause a spawn failure, so check a minimum build of each flag.
+///
+/// ```ignore
+/// use std::process::Command;
+/// use conpty::{ProcessOptions, Quirks};
+///
+/// let mut opts = ProcessOptions::default();
+/// opts.quirks(Quirks::INHERIT_CURSOR | Quirks::RESIZE_QUIRK);
+///
+/// let proc = opts.spawn(Command::new("cmd")).unwrap();
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Quirks(u32);
+
+impl Quirks {
+    /// No quirks are used.
+    pub const NONE: Self = Self(0);
+
+    /// `PSEUDOCONSOLE_INHERIT_CURSOR`.
+    ///
+    /// The pseudo console starts at the cursor position of the parent terminal.
+    /// To do so it sends a `ESC[6n` request to the output and
+    /// waits a `ESC[{row};{col}R` response on the input,
+    /// so a host must answer it otherwise the console hangs.
+    ///
+    /// Available since Windows 10 1809 (build 17763).
+    pub const INHERIT_CURSOR: Self = Self(0x1);
+
+    /// `PSEUDOCONSOLE_RESIZE_QUIRK`.
+    ///
+    /// Makes the console not repaint the whole buffer on resize,
+    /// which is the way Windows Terminal avoids duplicated lines in its scrollback.
+    ///
+    /// Available since Windows 11 (build 22000).
+    pub const RESIZE_QUIRK: Self = Self(0x2);
+
+    /// `PSEUDOCONSOLE_WIN32_INPUT_MODE`.
+    ///
+    /// Makes the console request win32-input-mode (`ESC[?9001h`),
+    /// so a host can send full `INPUT_RECORD` information as VT sequences.
+    ///
+    /// Available since Windows 11 (build 22000).
+    pub const WIN32_INPUT_MODE: Self = Self(0x4);
+
+    /// Constructs quirks from a raw `dwFlags` value.
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// Returns a raw `dwFlags` value.
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Verifies whether all flags of `other` are set.
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Verifies whether no flags are set.
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl ops::BitOr for Quirks {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl ops::BitOrAssign for Quirks {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl ops::BitAnd for Quirks {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl ops::Not for Quirks {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self(!self.0)
+    }
+}
+
+impl fmt::Debug for Quirks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [
+            (Self::INHERIT_CURSOR, "INHERIT_CURSOR"),
+            (Self::RESIZE_QUIRK, "RESIZE_QUIRK"),
+            (Self::WIN32_INPUT_MODE, "WIN32_INPUT_MODE"),
+        ];
+
+        let mut list = f.debug_set();
+        for (flag, name) in names {
+            if self.contains(flag) {
+                let _ = list.entry(&format_args!("{}", name));
+            }
+        }
+
+        let known = names.iter().fold(Self::NONE, |acc, (flag, _)| acc | *flag);
+        let unknown = *self & !known;
+        if !unknown.is_empty() {
+            let _ = list.entry(&format_args!("{:#x}", unknown.0));
+        }
+
+        list.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quirks_flags_test() {
+        let quirks = Quirks::INHERIT_CURSOR | Quirks::WIN32_INPUT_MODE;
+        assert_eq!(quirks.bits(), 0x5);
+        assert!(quirks.contains(Quirks::INHERIT_CURSOR));
+        assert!(!quirks.contains(Quirks::RESIZE_QUIRK));
+        assert!(Quirks::default().is_empty());
+    }
+
+    #[test]
+    fn quirks_debug_test() {
+        let quirks = Quirks::RESIZE_QUIRK | Quirks::from_bits(0x10);
+        assert_eq!(format!("{:?}", quirks), "{RESIZE_QUIRK, 0x10}");
+    }
+}
@@ -0,0 +1,111 @@
+//! Module contains a lookup of a program which is going to be run by `CreateProcessW`.
+
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    os::windows::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+};
+
+/// Returns a program name, which is the first token of a command line.
+pub(crate) fn program_name(commandline: &OsStr) -> OsString {
+    let wide: Vec<u16> = commandline.encode_wide().collect();
+    let is_space = |c: u16| c == ' ' as u16 || c == '\t' as u16;
+
+    let start = wide
+        .iter()
+        .position(|&c| !is_space(c))
+        .unwrap_or(wide.len());
+    let wide = &wide[start..];
+
+    let token = if wide.first() == Some(&('"' as u16)) {
+        let end = wide[1..]
+            .iter()
+            .position(|&c| c == '"' as u16)
+            .map_or(wide.len(), |pos| pos + 1);
+        &wide[1..end]
+    } else {
+        let end = wide.iter().position(|&c| is_space(c)).unwrap_or(wide.len());
+        &wide[..end]
+    };
+
+    OsString::from_wide(token)
+}
+
+/// Looks for a program the same way `CreateProcessW` does.
+///
+/// `.exe` is appended if a name has no extension.
+/// If a name is not a path it's searched in
+/// the current directory, the system directories and `PATH`.
+pub(crate) fn find_program(name: &OsStr, current_dir: Option<&Path>) -> Option<PathBuf> {
+    let mut name = PathBuf::from(name);
+    if name.as_os_str().is_empty() {
+        return None;
+    }
+
+    if name.extension().is_none() {
+        let _ = name.set_extension("exe");
+    }
+
+    let current_dir = match current_dir {
+        Some(dir) => Some(dir.to_owned()),
+        None => env::current_dir().ok(),
+    };
+
+    let is_path = name.components().count() > 1 || name.is_absolute();
+    if is_path {
+        let path = match &current_dir {
+            Some(dir) => dir.join(&name),
+            None => name,
+        };
+
+        return path.is_file().then_some(path);
+    }
+
+    let mut dirs = Vec::new();
+    dirs.extend(current_dir);
+    if let Some(root) = env::var_os("SystemRoot") {
+        let root = PathBuf::from(root);
+        dirs.push(root.join("System32"));
+        dirs.push(root);
+    }
+    if let Some(path) = env::var_os("PATH") {
+        dirs.extend(env::split_paths(&path));
+    }
+
+    dirs.into_iter()
+        .map(|dir| dir.join(&name))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn program_name_test() {
+        let tests = [
+            ("cmd /C echo", "cmd"),
+            ("cmd", "cmd"),
+            ("  python  script.py", "python"),
+            (
+                r#""C:\Program Files\app.exe" /C echo"#,
+                r"C:\Program Files\app.exe",
+            ),
+            (r#""C:\Program Files\app.exe"#, r"C:\Program Files\app.exe"),
+            ("", ""),
+        ];
+
+        for (commandline, expected) in tests {
+            assert_eq!(program_name(OsStr::new(commandline)), expected);
+        }
+    }
+
+    #[test]
+    fn find_program_test() {
+        assert!(find_program(OsStr::new("cmd"), None).is_some());
+        assert!(find_program(OsStr::new("cmd.exe"), None).is_some());
+        assert!(find_program(OsStr::new("not-existing-program-123"), None).is_none());
+        assert!(find_program(OsStr::new(""), None).is_none());
+    }
+}
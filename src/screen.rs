@@ -0,0 +1,400 @@
+//! Module contains a copy of a pseudo console screen which is rebuilt from its output.
+
+// Screen keeps a grid of characters which is updated by the VT output of a pseudo console.
+//
+// ConPTY renders its screen buffer as VT sequences,
+// so a subset of them is enough to keep a copy of the visible lines:
+// text, CR, LF, BS, TAB, cursor movement, erasing, inserting, deleting and scrolling.
+// Other sequences (e.g. colors and modes) are ignored.
+#[derive(Debug, Clone)]
+pub(crate) struct Screen {
+    cells: Vec<Vec<char>>,
+    columns: usize,
+    x: usize,
+    y: usize,
+    // the cursor is at the last column which was written, the next character wraps the line
+    wrap_pending: bool,
+    saved_cursor: (usize, usize),
+    state: State,
+    // parameters of a CSI sequence
+    params: Vec<u8>,
+    // a partially read UTF-8 character
+    utf8: Vec<u8>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum State {
+    #[default]
+    Ground,
+    // after ESC
+    Escape,
+    // after ESC and an intermediate byte (e.g. `ESC ( B`)
+    EscapeIntermediate,
+    // inside `ESC [`
+    Csi,
+    // inside a string terminated by BEL or ST (OSC, DCS, SOS, PM, APC)
+    String,
+    // after ESC inside a string
+    StringEscape,
+}
+
+const BLANK: char = ' ';
+const TAB_WIDTH: usize = 8;
+
+impl Screen {
+    pub(crate) fn new(columns: i16, rows: i16) -> Self {
+        let columns = columns.max(1) as usize;
+        let rows = rows.max(1) as usize;
+
+        Self {
+            cells: vec![vec![BLANK; columns]; rows],
+            columns,
+            x: 0,
+            y: 0,
+            wrap_pending: false,
+            saved_cursor: (0, 0),
+            state: State::Ground,
+            params: Vec::new(),
+            utf8: Vec::new(),
+        }
+    }
+
+    // Returns the lines of the screen, trailing blanks are trimmed.
+    pub(crate) fn lines(&self) -> Vec<String> {
+        self.cells
+            .iter()
+            .map(|row| row.iter().collect::<String>().trim_end().to_owned())
+            .collect()
+    }
+
+    // Changes the size keeping the top left part of the screen,
+    // ConPTY repaints the screen after a resize anyway.
+    pub(crate) fn resize(&mut self, columns: i16, rows: i16) {
+        self.columns = columns.max(1) as usize;
+        let rows = rows.max(1) as usize;
+
+        self.cells.resize(rows, Vec::new());
+        for row in &mut self.cells {
+            row.resize(self.columns, BLANK);
+        }
+
+        self.move_to(self.x, self.y);
+    }
+
+    // Applies an output, a sequence may be split across the calls.
+    pub(crate) fn feed(&mut self, input: &[u8]) {
+        for &b in input {
+            self.state = match (self.state, b) {
+                (State::Ground, 0x1B) => {
+                    self.utf8.clear();
+                    State::Escape
+                }
+                (State::Ground, _) => {
+                    self.ground(b);
+                    State::Ground
+                }
+                (State::Escape, b'[') => {
+                    self.params.clear();
+                    State::Csi
+                }
+                (State::Escape, b']' | b'P' | b'X' | b'^' | b'_') => State::String,
+                (State::Escape, 0x20..=0x2F) => State::EscapeIntermediate,
+                (State::Escape, _) => {
+                    self.escape(b);
+                    State::Ground
+                }
+                (State::EscapeIntermediate, 0x20..=0x2F) => State::EscapeIntermediate,
+                (State::EscapeIntermediate, _) => State::Ground,
+                (State::Csi, 0x20..=0x3F) => {
+                    self.params.push(b);
+                    State::Csi
+                }
+                (State::Csi, 0x40..=0x7E) => {
+                    self.csi(b);
+                    State::Ground
+                }
+                (State::Csi, _) => State::Ground,
+                (State::String, 0x07) => State::Ground,
+                (State::String, 0x1B) => State::StringEscape,
+                (State::String, _) => State::String,
+                (State::StringEscape, b'\\') => State::Ground,
+                (State::StringEscape, _) => State::String,
+            };
+        }
+    }
+
+    fn ground(&mut self, b: u8) {
+        if b >= 0x80 || !self.utf8.is_empty() {
+            self.utf8.push(b);
+            match std::str::from_utf8(&self.utf8) {
+                Ok(s) => {
+                    let c = s.chars().next().unwrap_or(char::REPLACEMENT_CHARACTER);
+                    self.utf8.clear();
+                    self.put(c);
+                }
+                Err(err) if err.error_len().is_some() => {
+                    self.utf8.clear();
+                    self.put(char::REPLACEMENT_CHARACTER);
+                }
+                // the character is not complete yet
+                Err(_) => {}
+            }
+
+            return;
+        }
+
+        match b {
+            b'\r' => self.move_to(0, self.y),
+            b'\n' | 0x0B | 0x0C => self.line_feed(),
+            0x08 => self.move_to(self.x.saturating_sub(1), self.y),
+            b'\t' => self.move_to((self.x / TAB_WIDTH + 1) * TAB_WIDTH, self.y),
+            0x20..=0x7E => self.put(b as char),
+            _ => {}
+        }
+    }
+
+    fn escape(&mut self, b: u8) {
+        match b {
+            b'7' => self.saved_cursor = (self.x, self.y),
+            b'8' => self.move_to(self.saved_cursor.0, self.saved_cursor.1),
+            b'D' => self.line_feed(),
+            b'E' => {
+                self.move_to(0, self.y);
+                self.line_feed();
+            }
+            b'M' => match self.y {
+                0 => self.scroll_down(1),
+                y => self.move_to(self.x, y - 1),
+            },
+            b'c' => *self = Self::new(self.columns as i16, self.rows() as i16),
+            _ => {}
+        }
+    }
+
+    fn csi(&mut self, action: u8) {
+        // private sequences (e.g. `ESC [ ? 25 l`) don't change the screen
+        if matches!(self.params.first(), Some(b'?' | b'>' | b'<' | b'=')) {
+            return;
+        }
+
+        let params = parse_params(&self.params);
+        let param = |i: usize| params.get(i).copied().unwrap_or(0);
+        let count = param(0).max(1);
+        let (x, y) = (self.x, self.y);
+
+        match action {
+            b'A' => self.move_to(x, y.saturating_sub(count)),
+            b'B' => self.move_to(x, y + count),
+            b'C' => self.move_to(x + count, y),
+            b'D' => self.move_to(x.saturating_sub(count), y),
+            b'E' => self.move_to(0, y + count),
+            b'F' => self.move_to(0, y.saturating_sub(count)),
+            b'G' | b'`' => self.move_to(count - 1, y),
+            b'd' => self.move_to(x, count - 1),
+            b'H' | b'f' => self.move_to(param(1).max(1) - 1, count - 1),
+            b'J' => match param(0) {
+                0 => {
+                    self.erase(y, x..self.columns);
+                    for row in y + 1..self.rows() {
+                        self.erase(row, 0..self.columns);
+                    }
+                }
+                1 => {
+                    for row in 0..y {
+                        self.erase(row, 0..self.columns);
+                    }
+                    self.erase(y, 0..x + 1);
+                }
+                _ => {
+                    for row in 0..self.rows() {
+                        self.erase(row, 0..self.columns);
+                    }
+                }
+            },
+            b'K' => match param(0) {
+                0 => self.erase(y, x..self.columns),
+                1 => self.erase(y, 0..x + 1),
+                _ => self.erase(y, 0..self.columns),
+            },
+            b'X' => self.erase(y, x..x + count),
+            b'P' => {
+                let row = &mut self.cells[y];
+                let count = count.min(self.columns - x);
+                let _ = row.drain(x..x + count);
+                row.resize(self.columns, BLANK);
+            }
+            b'@' => {
+                let row = &mut self.cells[y];
+                let count = count.min(self.columns - x);
+                for _ in 0..count {
+                    row.insert(x, BLANK);
+                }
+                row.truncate(self.columns);
+            }
+            b'L' => {
+                let count = count.min(self.rows() - y);
+                for _ in 0..count {
+                    let _ = self.cells.pop();
+                    self.cells.insert(y, vec![BLANK; self.columns]);
+                }
+            }
+            b'M' => {
+                let count = count.min(self.rows() - y);
+                for _ in 0..count {
+                    let _ = self.cells.remove(y);
+                    self.cells.push(vec![BLANK; self.columns]);
+                }
+            }
+            b'S' => self.scroll_up(count),
+            b'T' => self.scroll_down(count),
+            b's' => self.saved_cursor = (x, y),
+            b'u' => self.move_to(self.saved_cursor.0, self.saved_cursor.1),
+            _ => {}
+        }
+    }
+
+    fn put(&mut self, c: char) {
+        if self.wrap_pending {
+            self.move_to(0, self.y);
+            self.line_feed();
+        }
+
+        self.cells[self.y][self.x] = c;
+        match self.x + 1 < self.columns {
+            true => self.x += 1,
+            false => self.wrap_pending = true,
+        }
+    }
+
+    fn line_feed(&mut self) {
+        match self.y + 1 < self.rows() {
+            true => self.move_to(self.x, self.y + 1),
+            false => {
+                self.scroll_up(1);
+                self.move_to(self.x, self.y);
+            }
+        }
+    }
+
+    fn move_to(&mut self, x: usize, y: usize) {
+        self.x = x.min(self.columns - 1);
+        self.y = y.min(self.rows() - 1);
+        self.wrap_pending = false;
+    }
+
+    fn erase(&mut self, y: usize, columns: std::ops::Range<usize>) {
+        let end = columns.end.min(self.columns);
+        let start = columns.start.min(end);
+        self.cells[y][start..end].fill(BLANK);
+    }
+
+    fn scroll_up(&mut self, count: usize) {
+        for _ in 0..count.min(self.rows()) {
+            let _ = self.cells.remove(0);
+            self.cells.push(vec![BLANK; self.columns]);
+        }
+    }
+
+    fn scroll_down(&mut self, count: usize) {
+        for _ in 0..count.min(self.rows()) {
+            let _ = self.cells.pop();
+            self.cells.insert(0, vec![BLANK; self.columns]);
+        }
+    }
+
+    fn rows(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+// Parses `;` separated numeric parameters, a missing one is 0 and sub parameters are ignored.
+fn parse_params(params: &[u8]) -> Vec<usize> {
+    params
+        .split(|&b| b == b';')
+        .map(|param| {
+            param
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .fold(0usize, |n, b| {
+                    n.saturating_mul(10).saturating_add(usize::from(b - b'0'))
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen(columns: i16, rows: i16, output: &[u8]) -> Vec<String> {
+        let mut screen = Screen::new(columns, rows);
+        screen.feed(output);
+        screen.lines()
+    }
+
+    #[test]
+    fn text_test() {
+        assert_eq!(
+            screen(6, 3, b"ab\r\ncd\x1b[31me\x1b[0m\r\n\x1b]0;title\x07f"),
+            ["ab", "cde", "f"]
+        );
+        // a line is wrapped and the screen is scrolled
+        assert_eq!(screen(3, 2, b"abcdef\r\ngh"), ["def", "gh"]);
+        assert_eq!(screen(10, 1, "😁\tb\x08c".as_bytes()), ["😁       c"]);
+    }
+
+    #[test]
+    fn cursor_test() {
+        assert_eq!(
+            screen(5, 3, b"\x1b[2;3Hx\x1b[Hy\x1b[3;1Hz\x1b[Aw"),
+            ["y", " wx", "z"]
+        );
+        assert_eq!(
+            screen(5, 2, b"abc\x1b[2Dx\x1b[Cy\x1b[Bz"),
+            ["axcy", "    z"]
+        );
+        assert_eq!(screen(5, 1, b"abc\x1b7\x1b[Gx\x1b8y"), ["xbcy"]);
+    }
+
+    #[test]
+    fn erase_test() {
+        assert_eq!(screen(5, 2, b"abcde\r\nfghij\x1b[H\x1b[2J"), ["", ""]);
+        assert_eq!(screen(5, 2, b"abcde\r\nfghij\x1b[1;3H\x1b[J"), ["ab", ""]);
+        assert_eq!(screen(5, 1, b"abcde\x1b[3G\x1b[K"), ["ab"]);
+        assert_eq!(screen(5, 1, b"abcde\x1b[3G\x1b[1K"), ["   de"]);
+        assert_eq!(screen(5, 1, b"abcde\x1b[2G\x1b[2X"), ["a  de"]);
+        assert_eq!(screen(5, 1, b"abcde\x1b[2G\x1b[2P"), ["ade"]);
+        assert_eq!(screen(5, 1, b"abc\x1b[2G\x1b[2@"), ["a  bc"]);
+    }
+
+    #[test]
+    fn lines_test() {
+        assert_eq!(screen(3, 3, b"a\r\nb\r\nc\x1b[2;1H\x1b[L"), ["a", "", "b"]);
+        assert_eq!(screen(3, 3, b"a\r\nb\r\nc\x1b[1;1H\x1b[M"), ["b", "c", ""]);
+        assert_eq!(screen(3, 3, b"a\r\nb\r\nc\x1b[S"), ["b", "c", ""]);
+        assert_eq!(screen(3, 3, b"a\r\nb\r\nc\x1b[T"), ["", "a", "b"]);
+        assert_eq!(screen(3, 2, b"a\r\nb\x1b[H\x1bMc"), ["c", "a"]);
+    }
+
+    #[test]
+    fn split_sequence_test() {
+        let output = "\x1b[?25l\x1b[2;2H😁\x1b]0;t\x07x".as_bytes();
+        for split in 0..output.len() {
+            let mut screen = Screen::new(4, 2);
+            screen.feed(&output[..split]);
+            screen.feed(&output[split..]);
+            assert_eq!(screen.lines(), ["", " 😁x"]);
+        }
+    }
+
+    #[test]
+    fn resize_test() {
+        let mut screen = Screen::new(4, 2);
+        screen.feed(b"abcd\r\nefgh");
+        screen.resize(2, 3);
+        assert_eq!(screen.lines(), ["ab", "ef", ""]);
+
+        screen.feed(b"x");
+        assert_eq!(screen.lines(), ["ab", "ex", ""]);
+    }
+}
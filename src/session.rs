@@ -0,0 +1,132 @@
+//! Module contains a [PtySession] which bundles a process with its IO.
+
+use std::{
+    io::{self, Read, Write},
+    process::Command,
+};
+
+use crate::{
+    error::Error,
+    io::{PipeReader, PipeWriter},
+    Process, ProcessWaiter, ResizeHandle,
+};
+
+/// PtySession represents a terminal session.
+///
+/// It owns a [Process] along with a reader and a writer of it,
+/// and it implements [Read] and [Write] itself.
+///
+/// ```ignore
+/// use std::{io::prelude::*, process::Command};
+///
+/// let mut session = conpty::PtySession::spawn(Command::new("cmd")).unwrap();
+/// session.write_all(b"echo Hello World\r\n").unwrap();
+///
+/// let mut buf = [0; 1028];
+/// let n = session.read(&mut buf).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct PtySession {
+    process: Process,
+    reader: PipeReader,
+    writer: PipeWriter,
+}
+
+impl PtySession {
+    /// Spawns a command and creates a session for it.
+    pub fn spawn(command: Command) -> Result<Self, Error> {
+        Self::new(Process::spawn(command)?)
+    }
+
+    /// Creates a session for a given process.
+    pub fn new(mut process: Process) -> Result<Self, Error> {
+        let reader = process.output()?;
+        let writer = process.input()?;
+
+        Ok(Self {
+            process,
+            reader,
+            writer,
+        })
+    }
+
+    /// Returns a process's pid.
+    pub fn pid(&self) -> u32 {
+        self.process.pid()
+    }
+
+    /// Sets the session's reader to a non blocking mode.
+    pub fn blocking(&mut self, on: bool) {
+        self.reader.blocking(on);
+    }
+
+    /// Resizes virtual terminal.
+    pub fn resize(&mut self, x: i16, y: i16) -> Result<(), Error> {
+        self.process.resize(x, y)
+    }
+
+    /// Terminates the process.
+    ///
+    /// See [Process::kill].
+    pub fn kill(&mut self) -> Result<(), Error> {
+        self.process.kill()
+    }
+
+    /// Waits before process exists.
+    pub fn wait(&self, timeout_millis: Option<u32>) -> Result<u32, Error> {
+        self.process.wait(timeout_millis)
+    }
+
+    /// Is alive determines if a process is still running.
+    pub fn is_alive(&self) -> bool {
+        self.process.is_alive()
+    }
+
+    /// Returns a handle which can be used to resize the console from a different thread.
+    pub fn resize_handler(&self) -> ResizeHandle {
+        self.process.resize_handler()
+    }
+
+    /// Returns a standalone waiter for the process.
+    pub fn waiter(&self) -> Result<ProcessWaiter, Error> {
+        self.process.waiter()
+    }
+
+    /// Returns a new reader of the session output.
+    ///
+    /// All readers share the same pipe.
+    pub fn try_clone_reader(&self) -> Result<PipeReader, Error> {
+        self.reader.try_clone()
+    }
+
+    /// Returns a new writer to the session input.
+    pub fn try_clone_writer(&self) -> Result<PipeWriter, Error> {
+        self.writer.try_clone()
+    }
+
+    /// Returns the underlying process.
+    pub fn process(&self) -> &Process {
+        &self.process
+    }
+
+    /// Returns the underlying process.
+    pub fn process_mut(&mut self) -> &mut Process {
+        &mut self.process
+    }
+}
+
+impl Read for PtySession {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl Write for PtySession {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
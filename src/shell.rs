@@ -0,0 +1,110 @@
+//! Module contains a [Shell] which is used to run commands.
+
+use std::{
+    ffi::{OsStr, OsString},
+    sync::Mutex,
+};
+
+/// Shell which is used to run a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Shell {
+    /// `cmd.exe`, which is used by default.
+    #[default]
+    Cmd,
+    /// Windows PowerShell (`powershell.exe`).
+    PowerShell,
+    /// PowerShell 7+ (`pwsh.exe`).
+    Pwsh,
+    /// A command is run directly without any shell.
+    None,
+}
+
+impl Shell {
+    /// Builds a command line which runs `command` in the shell.
+    pub(crate) fn wrap(&self, command: &OsStr) -> OsString {
+        let prefix = match self {
+            Shell::Cmd => "cmd /C ",
+            Shell::PowerShell => "powershell -NoLogo -Command ",
+            Shell::Pwsh => "pwsh -NoLogo -Command ",
+            Shell::None => "",
+        };
+
+        let mut cmd = OsString::from(prefix);
+        cmd.push(command);
+        cmd
+    }
+}
+
+/// A mode in which `cmd.exe` runs a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CmdMode {
+    /// `cmd /C`, the shell exits once the command is done.
+    #[default]
+    RunAndClose,
+    /// `cmd /K`, the shell is kept open after the command is done.
+    RunAndKeepOpen,
+}
+
+impl CmdMode {
+    /// Builds a command line which runs `command` in `cmd.exe`.
+    pub(crate) fn wrap(&self, command: &OsStr) -> OsString {
+        let prefix = match self {
+            CmdMode::RunAndClose => "cmd /C ",
+            CmdMode::RunAndKeepOpen => "cmd /K ",
+        };
+
+        let mut cmd = OsString::from(prefix);
+        cmd.push(command);
+        cmd
+    }
+}
+
+static DEFAULT_SHELL: Mutex<Shell> = Mutex::new(Shell::Cmd);
+
+/// Sets a shell which is used by [crate::spawn].
+///
+/// The setting is global and can be changed from any thread,
+/// a spawn which is in progress uses the shell which was set before it started.
+/// It's used only by [crate::spawn], [crate::ProcessOptions] never uses it,
+/// a shell for it is set with [crate::ProcessOptions::shell].
+pub fn set_default_shell(shell: Shell) {
+    *DEFAULT_SHELL.lock().unwrap_or_else(|err| err.into_inner()) = shell;
+}
+
+/// Returns a shell which is used by [crate::spawn].
+///
+/// It's [Shell::Cmd] unless it was changed by [set_default_shell].
+pub fn default_shell() -> Shell {
+    *DEFAULT_SHELL.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_wrap_test() {
+        let tests = [
+            (Shell::Cmd, "cmd /C echo 1"),
+            (Shell::PowerShell, "powershell -NoLogo -Command echo 1"),
+            (Shell::Pwsh, "pwsh -NoLogo -Command echo 1"),
+            (Shell::None, "echo 1"),
+        ];
+
+        for (shell, expected) in tests {
+            assert_eq!(shell.wrap(OsStr::new("echo 1")), expected);
+        }
+    }
+
+    #[test]
+    fn cmd_mode_wrap_test() {
+        let tests = [
+            (CmdMode::RunAndClose, "cmd /C echo 1"),
+            (CmdMode::RunAndKeepOpen, "cmd /K echo 1"),
+        ];
+
+        for (mode, expected) in tests {
+            assert_eq!(mode.wrap(OsStr::new("echo 1")), expected);
+        }
+    }
+}
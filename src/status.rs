@@ -0,0 +1,114 @@
+//! Module contains an [ExitStatus] of a process.
+
+use std::{fmt, os::windows::process::ExitStatusExt};
+
+/// ExitStatus wraps an exit code of a process.
+///
+/// A process which was terminated abnormally returns a `NTSTATUS` code (e.g. `0xC000013A`),
+/// which is a negative number when interpreted as `i32`.
+///
+/// ```ignore
+/// let proc = conpty::spawn("echo Hello World").unwrap();
+/// let status = conpty::ExitStatus::from(proc.wait(None).unwrap());
+///
+/// assert!(status.success());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExitStatus(u32);
+
+impl ExitStatus {
+    /// Creates a status from an exit code.
+    pub const fn new(code: u32) -> Self {
+        Self(code)
+    }
+
+    /// Returns an exit code.
+    pub const fn code(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns an exit code interpreted as a signed number.
+    pub const fn code_i32(&self) -> i32 {
+        self.0 as i32
+    }
+
+    /// Verifies whether the exit code is 0.
+    pub const fn success(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns a name of a common `NTSTATUS` value if the code is one of them.
+    pub fn ntstatus_name(&self) -> Option<&'static str> {
+        NTSTATUS_NAMES
+            .iter()
+            .find(|(code, _)| *code == self.0)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl From<u32> for ExitStatus {
+    fn from(code: u32) -> Self {
+        Self(code)
+    }
+}
+
+impl From<ExitStatus> for std::process::ExitStatus {
+    fn from(status: ExitStatus) -> Self {
+        std::process::ExitStatus::from_raw(status.code())
+    }
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ntstatus_name() {
+            Some(name) => write!(f, "exit code: {:#X} ({})", self.0, name),
+            None => write!(f, "exit code: {}", self.0),
+        }
+    }
+}
+
+const NTSTATUS_NAMES: [(u32, &str); 17] = [
+    (0x8000_0003, "STATUS_BREAKPOINT"),
+    (0xC000_0005, "STATUS_ACCESS_VIOLATION"),
+    (0xC000_0008, "STATUS_INVALID_HANDLE"),
+    (0xC000_0017, "STATUS_NO_MEMORY"),
+    (0xC000_001D, "STATUS_ILLEGAL_INSTRUCTION"),
+    (0xC000_0025, "STATUS_NONCONTINUABLE_EXCEPTION"),
+    (0xC000_008E, "STATUS_FLOAT_DIVIDE_BY_ZERO"),
+    (0xC000_0094, "STATUS_INTEGER_DIVIDE_BY_ZERO"),
+    (0xC000_0095, "STATUS_INTEGER_OVERFLOW"),
+    (0xC000_0096, "STATUS_PRIVILEGED_INSTRUCTION"),
+    (0xC000_00FD, "STATUS_STACK_OVERFLOW"),
+    (0xC000_0135, "STATUS_DLL_NOT_FOUND"),
+    (0xC000_0139, "STATUS_ENTRYPOINT_NOT_FOUND"),
+    (0xC000_013A, "STATUS_CONTROL_C_EXIT"),
+    (0xC000_0142, "STATUS_DLL_INIT_FAILED"),
+    (0xC000_0374, "STATUS_HEAP_CORRUPTION"),
+    (0xC000_0409, "STATUS_STACK_BUFFER_OVERRUN"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ntstatus_test() {
+        let status = ExitStatus::new(0xC000_013A);
+        assert_eq!(status.code_i32(), -1073741510);
+        assert_eq!(status.ntstatus_name(), Some("STATUS_CONTROL_C_EXIT"));
+        assert_eq!(
+            status.to_string(),
+            "exit code: 0xC000013A (STATUS_CONTROL_C_EXIT)"
+        );
+        assert!(!status.success());
+    }
+
+    #[test]
+    fn regular_code_test() {
+        let status = ExitStatus::from(1);
+        assert_eq!(status.code_i32(), 1);
+        assert_eq!(status.ntstatus_name(), None);
+        assert_eq!(status.to_string(), "exit code: 1");
+        assert!(ExitStatus::new(0).success());
+    }
+}
@@ -1,17 +1,79 @@
+use std::io;
+
 use windows::core as win;
 use windows::Win32::{
-    Foundation::{DuplicateHandle, DUPLICATE_SAME_ACCESS, HANDLE},
-    System::Threading::GetCurrentProcess,
+    Foundation::{
+        CloseHandle, DuplicateHandle, SetHandleInformation, DUPLICATE_SAME_ACCESS,
+        ERROR_BROKEN_PIPE, ERROR_NO_DATA, ERROR_PIPE_NOT_CONNECTED, HANDLE, HANDLE_FLAGS,
+        HANDLE_FLAG_INHERIT,
+    },
+    System::{
+        Console::{GetConsoleProcessList, GetConsoleScreenBufferInfo, CONSOLE_SCREEN_BUFFER_INFO},
+        Diagnostics::Debug::IsDebuggerPresent,
+        Threading::GetCurrentProcess,
+    },
 };
 
+/// OwnedHandle closes a HANDLE on drop.
+#[derive(Debug)]
+pub(crate) struct OwnedHandle(pub(crate) HANDLE);
+
+impl OwnedHandle {
+    /// into_raw releases the HANDLE without closing it.
+    pub(crate) fn into_raw(self) -> HANDLE {
+        std::mem::ManuallyDrop::new(self).0
+    }
+}
+
+impl Drop for OwnedHandle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.0);
+        }
+    }
+}
+
+unsafe impl Send for OwnedHandle {}
+unsafe impl Sync for OwnedHandle {}
+
+/// read_hang_likely determines whether a blocking read of a pipe may hang after a process exit.
+///
+/// A debugger or `cargo-nextest` spawns the current process with inheritable handles,
+/// which may end up holding the write end of a pipe open,
+/// so a read never gets EOF even after the child exits.
+pub(crate) fn read_hang_likely() -> bool {
+    let debugger = unsafe { IsDebuggerPresent().as_bool() };
+    debugger || std::env::var_os("NEXTEST").is_some()
+}
+
+/// console_attached determines whether the current process has a console.
+///
+/// A `windows_subsystem = "windows"` application has no console unless it allocated one,
+/// so its std handles are invalid.
+///
+/// A console created with `CREATE_NO_WINDOW` has no window,
+/// so rather than a window the list of processes attached to the console is checked,
+/// which has at least the current process if there's a console.
+pub(crate) fn console_attached() -> bool {
+    let mut pids = [0u32; 1];
+    unsafe { GetConsoleProcessList(&mut pids) > 0 }
+}
+
 /// clone_handle can be used to clone a general HANDLE.
 pub(crate) fn clone_handle(handle: HANDLE) -> win::Result<HANDLE> {
+    duplicate_handle_into(handle, unsafe { GetCurrentProcess() })
+}
+
+/// duplicate_handle_into duplicates a HANDLE into a handle table of a target process.
+///
+/// The source HANDLE is not closed and the access rights are the same.
+pub(crate) fn duplicate_handle_into(handle: HANDLE, target_process: HANDLE) -> win::Result<HANDLE> {
     let mut cloned_handle = HANDLE::default();
     unsafe {
         DuplicateHandle(
             GetCurrentProcess(),
             handle,
-            GetCurrentProcess(),
+            target_process,
             &mut cloned_handle,
             0,
             false,
@@ -21,3 +83,39 @@ pub(crate) fn clone_handle(handle: HANDLE) -> win::Result<HANDLE> {
 
     Ok(cloned_handle)
 }
+
+/// disable_inheritance makes sure a HANDLE is not inherited by child processes.
+pub(crate) fn disable_inheritance(handle: HANDLE) -> win::Result<()> {
+    unsafe { SetHandleInformation(handle, HANDLE_FLAG_INHERIT.0, HANDLE_FLAGS(0)) }
+}
+
+/// enable_inheritance makes a HANDLE inheritable by child processes.
+pub(crate) fn enable_inheritance(handle: HANDLE) -> win::Result<()> {
+    unsafe { SetHandleInformation(handle, HANDLE_FLAG_INHERIT.0, HANDLE_FLAG_INHERIT) }
+}
+
+/// is_broken_pipe determines whether an IO error means the other end of a pipe is closed.
+///
+/// It's EOF for a reader and a closed input for a writer.
+pub(crate) fn is_broken_pipe(err: &io::Error) -> bool {
+    let code = err.raw_os_error();
+    code == Some(ERROR_BROKEN_PIPE.to_hresult().0)
+        || code == Some(ERROR_PIPE_NOT_CONNECTED.to_hresult().0)
+        || code == Some(ERROR_NO_DATA.to_hresult().0)
+}
+
+/// screen_buffer_info returns an information about a console screen buffer.
+pub(crate) fn screen_buffer_info(console: HANDLE) -> win::Result<CONSOLE_SCREEN_BUFFER_INFO> {
+    let mut info = CONSOLE_SCREEN_BUFFER_INFO::default();
+    unsafe { GetConsoleScreenBufferInfo(console, &mut info)? };
+
+    Ok(info)
+}
+
+/// window_size returns a size of a visible window (columns, rows) of a console screen buffer.
+pub(crate) fn window_size(info: &CONSOLE_SCREEN_BUFFER_INFO) -> (i16, i16) {
+    let columns = info.srWindow.Right - info.srWindow.Left + 1;
+    let rows = info.srWindow.Bottom - info.srWindow.Top + 1;
+
+    (columns, rows)
+}
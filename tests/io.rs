@@ -1,10 +1,12 @@
 use std::{
     io::{self, BufRead, BufReader, LineWriter, Read, Write},
+    process::Command,
+    sync::{Arc, Mutex},
     thread,
     time::Duration,
 };
 
-use conpty::spawn;
+use conpty::{io::BufPipeWriter, spawn, ProcessOptions, PtySession};
 use strip_ansi_escapes::strip;
 
 #[test]
@@ -78,6 +80,36 @@ fn read_until() {
     assert_eq!(strip(&buf[..n]).unwrap(), b"World");
 }
 
+#[test]
+fn read_until_without_buf_reader() {
+    let mut proc = spawn(r"python .\tests\util\cat.py").unwrap();
+    let mut writer = proc.input().unwrap();
+    let mut reader = proc.output().unwrap();
+
+    writeln!(writer, "Hello World").unwrap();
+
+    let mut buf = Vec::new();
+    reader.read_until(b' ', &mut buf).unwrap();
+    assert_eq!(strip(&buf).unwrap(), b"Hello ");
+
+    let mut buf = Vec::new();
+    reader.read_until(b'\n', &mut buf).unwrap();
+    assert_eq!(strip(&buf).unwrap(), b"World\r\n");
+}
+
+#[test]
+fn read_until_reaches_eof() {
+    let mut proc = spawn("echo Hello World").unwrap();
+    let mut reader = proc.output().unwrap();
+
+    proc.wait(None).unwrap();
+    drop(proc);
+
+    let mut buf = Vec::new();
+    reader.read_until(b'!', &mut buf).unwrap();
+    assert!(String::from_utf8_lossy(&buf).contains("Hello World"));
+}
+
 #[test]
 fn read_blocks_after_process_exit() {
     let mut proc = spawn(r"python .\tests\util\cat.py").unwrap();
@@ -146,17 +178,32 @@ fn read_to_end_blocks_after_process_exit() {
     );
 }
 
+#[test]
+fn broken_pipe_is_eof() {
+    let mut proc = spawn("echo Hello World").unwrap();
+    let mut reader = proc.output().unwrap();
+    proc.wait(None).unwrap();
+    proc.close_console();
+
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert_eq!(reader.read(&mut [0; 128]).unwrap(), 0);
+
+    reader.blocking(false);
+    assert_eq!(reader.read(&mut [0; 128]).unwrap(), 0);
+}
+
 fn try_pipe_read<R: FnOnce() -> io::Result<usize> + Send + 'static>(reader: R, timeout: Duration) {
     let handle = thread::spawn(move || {
         // Because reader will be dropped when the the reading is still active
-        // we might get a error that pipe has been closed.
+        // we might get EOF as the pipe has been closed.
         match reader() {
-            Err(err) if err.to_string() == "The pipe has been ended. (os error -2147024787)" => {}
-            result => {
+            Ok(_) => {}
+            Err(err) => {
                 // the error will be propagated in case of panic
                 panic!(
                     "it's unnexpected that read operation will be ended {:?}",
-                    result.unwrap_err().to_string()
+                    err.to_string()
                 )
             }
         }
@@ -167,3 +214,668 @@ fn try_pipe_read<R: FnOnce() -> io::Result<usize> + Send + 'static>(reader: R, t
 
     drop(handle);
 }
+
+#[test]
+fn spawn_output_only() {
+    let (waiter, reader) = conpty::spawn_output("echo Hello World").unwrap();
+
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert!(line.contains("Hello World"), "{:?}", line);
+
+    assert_eq!(waiter.wait(None).unwrap(), 0);
+}
+
+#[test]
+fn run_to_completion() {
+    let mut proc = spawn("echo Hello World").unwrap();
+    let (code, output) = proc.run_to_completion().unwrap();
+
+    assert_eq!(code, 0);
+    assert!(String::from_utf8_lossy(&output).contains("Hello World"));
+}
+
+#[test]
+fn scoped_read_and_wait() {
+    let mut proc = spawn("echo Hello World").unwrap();
+    let mut reader = BufReader::new(proc.output().unwrap());
+
+    thread::scope(|s| {
+        let line = s.spawn(|| {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            line
+        });
+        let code = s.spawn(|| proc.wait(None).unwrap());
+
+        assert_eq!(code.join().unwrap(), 0);
+        assert!(line.join().unwrap().contains("Hello World"));
+    });
+}
+
+#[test]
+fn write_input_records() {
+    use conpty::input::{InputRecord, KeyEvent};
+
+    let mut proc = spawn(r"python .\tests\util\cat.py").unwrap();
+    let mut reader = BufReader::new(proc.output().unwrap());
+
+    let key = |c: char, vk: u16| {
+        let event = KeyEvent {
+            key_down: true,
+            repeat_count: 1,
+            virtual_key_code: vk,
+            unicode_char: c as u16,
+            ..Default::default()
+        };
+
+        [
+            InputRecord::Key(event),
+            InputRecord::Key(KeyEvent {
+                key_down: false,
+                ..event
+            }),
+        ]
+    };
+
+    let mut records = Vec::new();
+    records.extend(key('h', 0x48));
+    records.extend(key('i', 0x49));
+    records.push(InputRecord::Resize {
+        columns: 100,
+        rows: 30,
+    });
+    records.extend(key('\r', 0x0D));
+
+    proc.write_input_records(&records).unwrap();
+
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert!(String::from_utf8_lossy(&strip(line.as_bytes()).unwrap()).contains("hi"));
+}
+
+#[test]
+fn buffered_writer() {
+    let mut proc = spawn(r"python .\tests\util\cat.py").unwrap();
+    let mut writer = BufPipeWriter::with_capacity(64, proc.input().unwrap());
+    let mut reader = BufReader::new(proc.output().unwrap());
+
+    for c in "Hello World".chars() {
+        write!(writer, "{}", c).unwrap();
+    }
+    assert_eq!(writer.buffer(), b"Hello World");
+
+    writer.write_all(b"\r\n").unwrap();
+    assert!(writer.buffer().is_empty());
+
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(strip(line.as_bytes()).unwrap(), b"Hello World\n");
+}
+
+#[test]
+fn pty_session() {
+    let mut session = PtySession::spawn(Command::new(r"python .\tests\util\cat.py")).unwrap();
+    assert!(session.is_alive());
+
+    session.write_all(b"Hello World\r\n").unwrap();
+
+    let mut reader = BufReader::new(session.try_clone_reader().unwrap());
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(strip(line.as_bytes()).unwrap(), b"Hello World\n");
+
+    session.kill().unwrap();
+    assert!(session.wait(Some(1000)).is_ok());
+}
+
+#[test]
+fn output_channel() {
+    let mut proc = spawn("echo Hello World").unwrap();
+    let rx = proc.output_channel().unwrap();
+
+    let output = rx.into_iter().flatten().collect::<Vec<_>>();
+    assert!(String::from_utf8_lossy(&output).contains("Hello World"));
+}
+
+#[test]
+fn output_channel_bounded() {
+    let mut proc = spawn("echo Hello World").unwrap();
+    let rx = proc.output_channel_bounded(1).unwrap();
+
+    thread::sleep(Duration::from_millis(500));
+
+    let output = rx.into_iter().flatten().collect::<Vec<_>>();
+    assert!(String::from_utf8_lossy(&output).contains("Hello World"));
+}
+
+#[test]
+fn write_right_after_spawn() {
+    let mut proc = ProcessOptions::default()
+        .wait_connected(Duration::from_secs(5))
+        .spawn(Command::new(r"python .\tests\util\cat.py"))
+        .unwrap();
+
+    let mut writer = proc.input().unwrap();
+    writer.write_all(b"first command\r\n").unwrap();
+
+    let mut reader = BufReader::new(proc.output().unwrap());
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert!(String::from_utf8_lossy(&strip(line.as_bytes()).unwrap()).contains("first command"));
+}
+
+#[test]
+fn run_timeout() {
+    let (code, output) = conpty::run_timeout("echo Hello World", Duration::from_secs(10)).unwrap();
+    assert_eq!(code, 0);
+    assert!(output.contains("Hello World"), "{:?}", output);
+    assert!(!output.contains('\x1b'), "{:?}", output);
+
+    let result = conpty::run_timeout("ping -n 30 127.0.0.1", Duration::from_millis(500));
+    assert!(matches!(result, Err(conpty::error::Error::Timeout(_))));
+}
+
+#[test]
+fn wait_with_output() {
+    let proc = spawn("echo Hello World && exit 3").unwrap();
+    let output = proc.wait_with_output().unwrap();
+    assert_eq!(output.status, 3);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Hello World"));
+
+    // the output is bigger than a pipe buffer
+    let proc = spawn("for /L %i in (1,1,2000) do @echo %i line of a long output").unwrap();
+    let output = proc.wait_with_output().unwrap();
+    assert_eq!(output.status, 0);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("2000 line"));
+}
+
+#[test]
+fn text_lines() {
+    let mut proc = spawn("echo Hello World").unwrap();
+    let lines = proc.text_lines().unwrap();
+
+    let lines = lines.collect::<io::Result<Vec<_>>>().unwrap();
+    assert!(
+        lines.iter().any(|line| line == "Hello World"),
+        "{:?}",
+        lines
+    );
+}
+
+#[test]
+fn write_vectored() {
+    let mut proc = spawn(r"python .\tests\util\cat.py").unwrap();
+    let mut writer = proc.input().unwrap();
+    let mut reader = BufReader::new(proc.output().unwrap());
+
+    let bufs = [
+        io::IoSlice::new(b"hello"),
+        io::IoSlice::new(b""),
+        io::IoSlice::new(b" vectored"),
+        io::IoSlice::new(b" write\r\n"),
+    ];
+    let n = writer.write_vectored(&bufs).unwrap();
+    assert_eq!(n, 21);
+
+    let mut line = String::new();
+    while !line.contains("hello vectored write") {
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+    }
+}
+
+#[test]
+fn read_vectored() {
+    let mut proc = spawn(r"python .\tests\util\cat.py").unwrap();
+    let mut writer = proc.input().unwrap();
+    let mut reader = proc.output().unwrap();
+
+    writer.write_all(b"hello vectored read\r\n").unwrap();
+
+    let mut output = Vec::new();
+    while !String::from_utf8_lossy(&strip(&output).unwrap()).contains("hello vectored read\n") {
+        let mut first = [0; 4];
+        let mut second = [0; 1024];
+        let mut bufs = [
+            io::IoSliceMut::new(&mut first),
+            io::IoSliceMut::new(&mut second),
+        ];
+        let n = reader.read_vectored(&mut bufs).unwrap();
+        assert_ne!(n, 0);
+
+        // the data fills the buffers in order
+        let first_n = n.min(first.len());
+        output.extend_from_slice(&first[..first_n]);
+        output.extend_from_slice(&second[..n - first_n]);
+    }
+
+    // a non blocking mode is respected
+    reader.blocking(false);
+    let mut buf = [0; 1024];
+    while reader.read(&mut buf).is_ok() {}
+    let mut first = [0; 4];
+    let mut second = [0; 4];
+    let mut bufs = [
+        io::IoSliceMut::new(&mut first),
+        io::IoSliceMut::new(&mut second),
+    ];
+    let err = reader.read_vectored(&mut bufs).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+}
+
+#[test]
+fn output_lines() {
+    let mut proc = spawn("echo Hello World").unwrap();
+    let lines = proc.output_lines(false).unwrap();
+
+    let lines = lines.collect::<io::Result<Vec<_>>>().unwrap();
+    assert!(lines.iter().all(|line| !line.ends_with('\r')));
+    assert!(
+        lines.iter().any(|line| line.contains("Hello World")),
+        "{:?}",
+        lines
+    );
+}
+
+#[test]
+fn read_timeout() {
+    let mut proc = spawn(r"python .\tests\util\cat.py").unwrap();
+    let mut writer = proc.input().unwrap();
+    let mut reader = proc.output().unwrap();
+
+    // drain the initial output
+    let mut buf = [0; 1024];
+    while reader
+        .read_timeout(&mut buf, Duration::from_millis(500))
+        .is_ok()
+    {}
+
+    let err = reader
+        .read_timeout(&mut buf, Duration::from_millis(100))
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(300));
+        writer.write_all(b"Hello World\r\n").unwrap();
+    });
+
+    // the output has VT sequences, so the count is checked on the plain text
+    let mut output = Vec::new();
+    while !strip(&output).unwrap().contains(&b'\n') {
+        let n = reader
+            .read_timeout(&mut buf, Duration::from_secs(5))
+            .unwrap();
+        assert_ne!(n, 0);
+        output.extend_from_slice(&buf[..n]);
+    }
+
+    let text = strip(&output).unwrap();
+    let line = text.split(|&b| b == b'\n').next().unwrap();
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    assert_eq!(line.len(), b"Hello World".len());
+    assert_eq!(line, b"Hello World");
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn read_exact_timeout() {
+    let mut proc = spawn(r"python .\tests\util\cat.py").unwrap();
+    let mut writer = proc.input().unwrap();
+    let mut reader = proc.output().unwrap();
+
+    // drain the initial output
+    let mut buf = [0; 1024];
+    while reader
+        .read_timeout(&mut buf, Duration::from_millis(500))
+        .is_ok()
+    {}
+
+    let err = reader
+        .read_exact_timeout(&mut buf, Duration::from_millis(100))
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+    // the data comes in several chunks
+    let handle = thread::spawn(move || {
+        for _ in 0..3 {
+            writer.write_all(b"Hello\r\n").unwrap();
+            thread::sleep(Duration::from_millis(100));
+        }
+    });
+
+    let mut buf = [0; 8];
+    reader
+        .read_exact_timeout(&mut buf, Duration::from_secs(5))
+        .unwrap();
+
+    handle.join().unwrap();
+
+    let mut proc = spawn("cmd /C echo Hello").unwrap();
+    let mut reader = proc.output().unwrap();
+    proc.wait(None).unwrap();
+    drop(proc);
+
+    let mut buf = [0; 1 << 20];
+    let err = reader
+        .read_exact_timeout(&mut buf, Duration::from_secs(5))
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn plain_text_reader() {
+    let mut proc = spawn("echo Hello World").unwrap();
+    let reader = proc.output().unwrap();
+    let mut reader = conpty::io::PlainTextReader::new(reader);
+    proc.wait(None).unwrap();
+
+    let mut buf = [0; 1024];
+    let n = reader.read(&mut buf).unwrap();
+    assert!(!buf[..n].contains(&b'\x1b'));
+    assert!(String::from_utf8_lossy(&buf[..n]).contains("Hello World"));
+}
+
+#[test]
+fn overlapped_io() {
+    let mut proc = ProcessOptions::default()
+        .overlapped_io(true)
+        .spawn(Command::new(r"python .\tests\util\cat.py"))
+        .unwrap();
+    let mut writer = proc.input().unwrap();
+    let reader = proc.output().unwrap();
+    assert!(reader.is_overlapped());
+
+    let mut read = reader.read_overlapped(1024).unwrap();
+    writer.write_all(b"hello cat\r\n").unwrap();
+
+    let mut output = Vec::new();
+    while !String::from_utf8_lossy(&strip(&output).unwrap()).contains("hello cat\n") {
+        match read.poll().unwrap() {
+            Some(chunk) => {
+                output.extend(chunk);
+                read = reader.read_overlapped(1024).unwrap();
+            }
+            None => thread::sleep(Duration::from_millis(10)),
+        }
+    }
+
+    // a pending read is cancelled on drop
+    drop(read);
+
+    // a regular read works as well
+    let mut reader = BufReader::new(reader);
+    writer.write_all(b"hello again\r\n").unwrap();
+    let mut line = String::new();
+    while !line.contains("hello again") {
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+    }
+
+    let mut proc = spawn("echo Hello World").unwrap();
+    let reader = proc.output().unwrap();
+    let err = reader.read_overlapped(1024).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test(flavor = "multi_thread")]
+async fn async_pipe_io() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut proc = spawn(r"python .\tests\util\cat.py").unwrap();
+    let mut writer = proc.input_async().unwrap();
+    let mut reader = proc.output_async().unwrap();
+
+    writer.write_all(b"hello tokio\r\n").await.unwrap();
+    writer.flush().await.unwrap();
+
+    let mut output = Vec::new();
+    while !String::from_utf8_lossy(&strip(&output).unwrap()).contains("hello tokio\n") {
+        let n = reader.read_buf(&mut output).await.unwrap();
+        assert_ne!(n, 0);
+    }
+}
+
+#[cfg(feature = "async-io")]
+#[test]
+fn futures_pipe_reader() {
+    use futures::AsyncReadExt;
+
+    let mut proc = spawn("echo Hello World").unwrap();
+    let mut reader = conpty::io::FuturesPipeReader::new(proc.output().unwrap());
+
+    let output = futures::executor::block_on(async {
+        let mut output = Vec::new();
+        while !String::from_utf8_lossy(&strip(&output).unwrap()).contains("Hello World") {
+            let mut buf = [0; 1024];
+            let n = reader.read(&mut buf).await.unwrap();
+            assert_ne!(n, 0);
+            output.extend_from_slice(&buf[..n]);
+        }
+
+        output
+    });
+
+    assert!(String::from_utf8_lossy(&output).contains("Hello World"));
+}
+
+#[test]
+fn output_shared() {
+    let mut proc = spawn(r#"cmd /C "for /L %i in (1,1,20) do @echo line %i""#).unwrap();
+    let reader = proc.output_shared().unwrap();
+    reader.lock().unwrap().blocking(false);
+
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let threads = (0..2)
+        .map(|_| {
+            let reader = reader.clone();
+            let output = output.clone();
+            thread::spawn(move || {
+                let mut buf = [0; 16];
+                loop {
+                    // the output is collected under the reader's lock to keep the order of chunks
+                    let mut reader = reader.lock().unwrap();
+                    let mut output = output.lock().unwrap();
+                    if String::from_utf8_lossy(&strip(&*output).unwrap()).contains("line 20") {
+                        break;
+                    }
+
+                    match reader.read(&mut buf) {
+                        Ok(n) => output.extend_from_slice(&buf[..n]),
+                        Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                            drop(output);
+                            drop(reader);
+                            thread::sleep(Duration::from_millis(10));
+                        }
+                        Err(err) => panic!("{}", err),
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let output = output.lock().unwrap();
+    let output = String::from_utf8_lossy(&strip(&*output).unwrap()).into_owned();
+    let lines = output
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| line.starts_with("line "))
+        .collect::<Vec<_>>();
+    let expected = (1..=20).map(|i| format!("line {}", i)).collect::<Vec<_>>();
+    assert_eq!(lines, expected);
+}
+
+#[test]
+fn close_input() {
+    let mut proc = spawn("sort").unwrap();
+    let mut reader = proc.output().unwrap();
+    proc.write_input(b"b\ra\r").unwrap();
+
+    proc.close_input().unwrap();
+    proc.close_input().unwrap();
+    assert!(matches!(
+        proc.input(),
+        Err(conpty::error::Error::InputClosed)
+    ));
+
+    // the process gets EOF, prints the sorted input and exits
+    assert_eq!(proc.wait(Some(5000)).unwrap(), 0);
+    drop(proc);
+
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    let output = String::from_utf8_lossy(&strip(&buf).unwrap()).into_owned();
+    let lines = output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+
+    // the echoed input is `b`, `a`
+    assert!(lines.windows(2).any(|w| w == ["a", "b"]), "{:?}", lines);
+}
+
+#[test]
+fn stdin_from() {
+    let path = std::env::temp_dir().join("conpty_stdin_from.txt");
+    std::fs::write(&path, "echo Hello World\rexit 3\r").unwrap();
+    let file = std::fs::File::open(&path).unwrap();
+
+    let mut proc = ProcessOptions::default()
+        .stdin_from(file.into())
+        .spawn(Command::new("cmd"))
+        .unwrap();
+    assert!(matches!(
+        proc.input(),
+        Err(conpty::error::Error::InputClosed)
+    ));
+
+    let mut reader = proc.output().unwrap();
+    // the whole input is run, rather than the console being closed on EOF of the file
+    assert_eq!(proc.wait(Some(5000)).unwrap(), 3);
+    drop(proc);
+
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert!(String::from_utf8_lossy(&strip(&buf).unwrap()).contains("Hello World"));
+
+    // the pumping thread may still hold the file
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn tee_output_to() {
+    let path = std::env::temp_dir().join("conpty_tee_output_to.txt");
+
+    let mut proc = spawn("echo Hello World").unwrap();
+    proc.tee_output_to(&path).unwrap();
+    let mut reader = proc.output().unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 0);
+    drop(proc);
+
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert!(String::from_utf8_lossy(&buf).contains("Hello World"));
+
+    // the readers get EOF only after the relay is done, so the recording is complete
+    let recorded = std::fs::read(&path).unwrap();
+    assert_eq!(recorded, buf);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn write_input() {
+    let mut proc = spawn("cmd").unwrap();
+    let mut reader = proc.output().unwrap();
+
+    assert_eq!(proc.write_input(b"echo Hello World\r").unwrap(), 17);
+    assert_eq!(proc.write_input(b"exit\r").unwrap(), 5);
+    proc.wait(Some(5000)).unwrap();
+
+    proc.close_input().unwrap();
+    assert!(matches!(
+        proc.write_input(b"echo Hello World\r"),
+        Err(conpty::error::Error::InputClosed)
+    ));
+    drop(proc);
+
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert!(String::from_utf8_lossy(&strip(&buf).unwrap()).contains("Hello World"));
+}
+
+#[test]
+fn drain_output() {
+    // the marker is expanded by cmd, so the typed command doesn't contain it
+    const MARKER: &str = "drain_Windows_NT_marker";
+
+    let mut proc = spawn("cmd").unwrap();
+    let mut reader = proc.output().unwrap();
+    proc.write_input(b"echo drain_%OS%_marker\r").unwrap();
+
+    let mut output = Vec::new();
+    let mut buf = [0; 1024];
+    while !String::from_utf8_lossy(&strip(&output).unwrap()).contains(MARKER) {
+        let n = reader
+            .read_timeout(&mut buf, Duration::from_secs(5))
+            .unwrap();
+        assert_ne!(n, 0);
+        output.extend_from_slice(&buf[..n]);
+    }
+
+    let _ = proc.drain_output().unwrap();
+
+    proc.write_input(b"echo Bye\r").unwrap();
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        assert_ne!(reader.read_line(&mut line).unwrap(), 0);
+        let line = String::from_utf8_lossy(&strip(line.as_bytes()).unwrap()).into_owned();
+        assert!(!line.contains(MARKER));
+        if line.contains("Bye") {
+            break;
+        }
+    }
+}
+
+#[test]
+fn pipe_buffer_size() {
+    fn count_reads(opts: &ProcessOptions) -> usize {
+        let cmd = r#"python -c "print('x' * 200000); print('done')""#;
+        let mut proc = opts.spawn(Command::new(cmd)).unwrap();
+        let mut reader = proc.output().unwrap();
+
+        // let the console fill the pipe
+        thread::sleep(Duration::from_secs(1));
+
+        let mut reads = 0;
+        let mut output = Vec::new();
+        let mut buf = vec![0; 1 << 20];
+        while !String::from_utf8_lossy(&strip(&output).unwrap()).contains("done") {
+            let n = reader.read(&mut buf).unwrap();
+            output.extend_from_slice(&buf[..n]);
+            reads += 1;
+        }
+
+        reads
+    }
+
+    let default_reads = count_reads(&ProcessOptions::default());
+    let large_reads = count_reads(ProcessOptions::default().pipe_buffer_size(1 << 20));
+    assert!(
+        large_reads <= default_reads,
+        "{} > {}",
+        large_reads,
+        default_reads
+    );
+}
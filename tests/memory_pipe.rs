@@ -0,0 +1,50 @@
+#![cfg(feature = "test-util")]
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use conpty::io::{MemoryPipe, PtyIo};
+
+// An example of a code which is generic over the pty IO.
+fn ask<R, W>(reader: R, writer: &mut W, question: &str) -> io::Result<String>
+where
+    R: Read + PtyIo,
+    W: Write + PtyIo,
+{
+    writeln!(writer, "{}", question)?;
+
+    let mut line = String::new();
+    BufReader::new(reader).read_line(&mut line)?;
+
+    Ok(line)
+}
+
+#[test]
+fn memory_pipe_as_pty_io() {
+    let pipe = MemoryPipe::new();
+    let mut writer = pipe.try_clone_io().unwrap();
+
+    let answer = ask(pipe, &mut writer, "Hello World").unwrap();
+    assert_eq!(answer, "Hello World\n");
+}
+
+#[test]
+fn memory_pipe_eof_after_close() {
+    let mut pipe = MemoryPipe::new();
+    pipe.write_all(b"Hello").unwrap();
+    pipe.close();
+
+    let mut buf = Vec::new();
+    pipe.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, b"Hello");
+
+    assert!(pipe.write(b"World").is_err());
+}
+
+#[test]
+fn memory_pipe_non_blocking() {
+    let mut pipe = MemoryPipe::new();
+    pipe.blocking(false);
+
+    let err = pipe.read(&mut [0; 128]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+}
@@ -1,6 +1,9 @@
-use std::io::{Read, Write};
+use std::{
+    io::{self, Read, Write},
+    process::Command,
+};
 
-use conpty::spawn;
+use conpty::{spawn, ProcessOptions};
 
 #[test]
 pub fn close_one_pty_input_doesnt_close_others() {
@@ -55,3 +58,96 @@ pub fn dropping_one_reader_doesnt_affect_others() {
         std::io::ErrorKind::WouldBlock
     );
 }
+
+#[test]
+pub fn duplicate_into_current_process() {
+    use std::os::windows::io::FromRawHandle;
+    use windows::Win32::System::Threading::GetCurrentProcess;
+
+    let mut proc = spawn("echo Hello World").unwrap();
+    let reader = proc.output().unwrap();
+
+    let handle = reader
+        .duplicate_into(unsafe { GetCurrentProcess() })
+        .unwrap();
+    drop(reader);
+
+    let mut file = unsafe { std::fs::File::from_raw_handle(handle) };
+    let mut buf = [0; 1028];
+    let n = file.read(&mut buf).unwrap();
+    assert!(String::from_utf8_lossy(&buf[..n]).contains("Hello World"));
+}
+
+#[test]
+pub fn clone_with_blocking() {
+    let mut proc = spawn("cmd").unwrap();
+    let mut reader = proc.output().unwrap();
+    reader.blocking(false);
+
+    assert!(reader.try_clone().unwrap().is_blocking());
+    assert!(!reader.clone_nonblocking().unwrap().is_blocking());
+    assert!(reader.clone_with_blocking(true).unwrap().is_blocking());
+
+    let mut clone = reader.clone_nonblocking().unwrap();
+    drop(reader);
+    loop {
+        match clone.read(&mut [0; 128]) {
+            Ok(_) => break,
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {}
+            Err(err) => panic!("{:?}", err),
+        }
+    }
+}
+
+#[test]
+pub fn bytes_available() {
+    let mut proc = spawn("echo Hello World").unwrap();
+    let mut reader = proc.output().unwrap();
+
+    while reader.bytes_available().unwrap() == 0 {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let available = reader.bytes_available().unwrap();
+    let mut buf = vec![0; available];
+    assert_eq!(reader.read(&mut buf).unwrap(), available);
+}
+
+#[test]
+pub fn into_owned_handle() {
+    use std::os::windows::io::OwnedHandle;
+
+    let mut proc = spawn(r"python .\tests\util\cat.py").unwrap();
+    let writer = OwnedHandle::from(proc.input().unwrap());
+    let reader = OwnedHandle::from(proc.output().unwrap());
+
+    let mut writer = std::fs::File::from(writer);
+    let mut reader = std::fs::File::from(reader);
+
+    writer.write_all(b"Hello World\r\n").unwrap();
+    assert!(reader.read(&mut [0; 128]).unwrap() > 0);
+}
+
+#[test]
+pub fn borrow_as_file() {
+    let mut proc = spawn("echo Hello World").unwrap();
+    let reader = proc.output().unwrap();
+
+    {
+        let file = reader.borrow_as_file().unwrap();
+        let mut buf = [0; 1028];
+        let n = (&*file).read(&mut buf).unwrap();
+        assert!(n > 0);
+    }
+
+    // the handle is still valid after the file was dropped
+    assert!(reader.bytes_available().is_ok());
+
+    let mut proc = ProcessOptions::default()
+        .overlapped_io(true)
+        .spawn(Command::new("cmd /C echo Hello World"))
+        .unwrap();
+    let reader = proc.output().unwrap();
+    let err = reader.borrow_as_file().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+}
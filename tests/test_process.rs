@@ -1,10 +1,13 @@
 use std::{
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, Read, Write},
     path::Path,
-    process::Command,
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::Duration,
 };
 
-use conpty::Process;
+use conpty::{error::Error, CmdMode, Process, ProcessOptions, Quirks, Shell};
 
 #[test]
 pub fn envs() {
@@ -85,3 +88,914 @@ pub fn test_current_dir() {
         }
     }
 }
+
+#[test]
+pub fn waiter_in_another_thread() {
+    let mut proc = Process::spawn(Command::new("cmd /C echo Hello World")).unwrap();
+    let waiter = proc.waiter().unwrap();
+    assert_eq!(waiter.pid(), proc.pid());
+
+    let handle = std::thread::spawn(move || waiter.wait(None).unwrap());
+
+    let mut reader = BufReader::new(proc.output().unwrap());
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+
+    assert_eq!(handle.join().unwrap(), 0);
+}
+
+#[test]
+pub fn waiter_outlives_process() {
+    let proc = Process::spawn(Command::new("cmd")).unwrap();
+    let waiter = proc.waiter().unwrap();
+    assert!(waiter.is_alive());
+
+    drop(proc);
+
+    assert!(waiter.wait(Some(5000)).is_ok());
+    assert!(!waiter.is_alive());
+}
+
+#[test]
+pub fn spawn_with_quirks() {
+    let mut opts = ProcessOptions::default();
+    opts.quirks(Quirks::RESIZE_QUIRK);
+
+    let mut proc = opts.spawn(Command::new("cmd /C echo Hello World")).unwrap();
+    let mut reader = BufReader::new(proc.output().unwrap());
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+
+    assert!(line.contains("Hello World"), "{:?}", line);
+    assert_eq!(proc.wait(None).unwrap(), 0);
+}
+
+#[test]
+pub fn display() {
+    let proc = Process::spawn(Command::new("cmd")).unwrap();
+    assert_eq!(
+        proc.to_string(),
+        format!("Process(pid={}, alive)", proc.pid())
+    );
+
+    let proc = Process::spawn(Command::new("cmd /C exit")).unwrap();
+    proc.wait(None).unwrap();
+    assert_eq!(
+        proc.to_string(),
+        format!("Process(pid={}, exited)", proc.pid())
+    );
+}
+
+#[test]
+pub fn program_path_with_spaces() {
+    let dir = std::env::temp_dir().join("conpty test dir");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let program = dir.join("my cmd.exe");
+    let system_root = std::env::var_os("SystemRoot").unwrap();
+    let cmd_path = Path::new(&system_root).join("System32").join("cmd.exe");
+    std::fs::copy(cmd_path, &program).unwrap();
+
+    let mut cmd = Command::new(&program);
+    cmd.args(["/C", "exit", "5"]);
+
+    let mut opts = ProcessOptions::default();
+    opts.program_path(&program);
+
+    let proc = opts.spawn(cmd).unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 5);
+}
+
+#[test]
+pub fn wait_with_progress() {
+    let proc = Process::spawn(Command::new("cmd /C ping -n 2 127.0.0.1")).unwrap();
+
+    let mut ticks = 0;
+    let code = proc
+        .wait_with_progress(Duration::from_millis(100), || ticks += 1)
+        .unwrap();
+
+    assert_eq!(code, 0);
+    assert!(ticks > 0);
+}
+
+#[test]
+pub fn resize_from_another_thread() {
+    let mut proc = Process::spawn(Command::new("cmd")).unwrap();
+    let handle = proc.resize_handler();
+
+    let resizer = std::thread::spawn(move || handle.resize(120, 40));
+
+    let mut reader = proc.output().unwrap();
+    reader.blocking(false);
+    let _ = reader.read(&mut [0; 128]);
+
+    assert!(resizer.join().unwrap().is_ok());
+}
+
+#[test]
+pub fn resize_handle_after_process_drop() {
+    let proc = Process::spawn(Command::new("cmd")).unwrap();
+    let handle = proc.resize_handler();
+
+    drop(proc);
+
+    assert!(matches!(handle.resize(120, 40), Err(Error::ConsoleClosed)));
+}
+
+#[test]
+pub fn spawn_with_options_shell() {
+    let mut opts = ProcessOptions::default();
+    opts.shell(Shell::PowerShell);
+
+    let proc = opts.spawn(Command::new("exit 3")).unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 3);
+}
+
+#[test]
+pub fn spawn_with_shell() {
+    let proc = conpty::spawn_with_shell(Shell::PowerShell, "exit 5").unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 5);
+
+    let proc = conpty::spawn_with_shell(Shell::None, "cmd /C exit 6").unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 6);
+}
+
+#[test]
+pub fn spawn_cmd() {
+    let proc = conpty::spawn_cmd("exit 5", CmdMode::RunAndClose).unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 5);
+
+    let mut proc = conpty::spawn_cmd("echo Hello", CmdMode::RunAndKeepOpen).unwrap();
+    assert!(matches!(proc.wait(Some(500)), Err(Error::Timeout(_))));
+    proc.input().unwrap().write_all(b"exit 6\r\n").unwrap();
+    assert_eq!(proc.wait(Some(5000)).unwrap(), 6);
+}
+
+#[test]
+pub fn spawn_with_default_shell() {
+    // the global shell is not changed here, as other tests spawn with it in parallel
+    assert_eq!(conpty::default_shell(), Shell::Cmd);
+
+    // the `cmd` syntax which PowerShell doesn't understand
+    let proc = conpty::spawn("if 1==1 (exit 4)").unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 4);
+}
+
+#[test]
+pub fn keep_console_on_exit() {
+    let mut opts = ProcessOptions::default();
+    opts.keep_console_on_exit(true);
+
+    let mut proc = opts.spawn(Command::new("cmd /C echo Hello World")).unwrap();
+    let reader = proc.output().unwrap();
+    let handle = proc.resize_handler();
+
+    assert_eq!(proc.wait(None).unwrap(), 0);
+    drop(proc);
+
+    // the console is kept alive by the reader
+    assert!(handle.resize(100, 30).is_ok());
+
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert!(line.contains("Hello World"), "{:?}", line);
+}
+
+#[test]
+pub fn close_console() {
+    let mut opts = ProcessOptions::default();
+    opts.keep_console_on_exit(true);
+
+    let mut proc = opts.spawn(Command::new("cmd")).unwrap();
+    let _reader = proc.output().unwrap();
+    let handle = proc.resize_handler();
+
+    proc.close_console();
+
+    assert!(matches!(handle.resize(100, 30), Err(Error::ConsoleClosed)));
+}
+
+#[test]
+pub fn resolve_not_existing_program() {
+    let mut opts = ProcessOptions::default();
+    opts.resolve_program(true);
+
+    let err = opts
+        .spawn(Command::new("not-existing-program-123 --help"))
+        .unwrap_err();
+    assert!(
+        matches!(&err, Error::ProgramNotFound(program) if program == "not-existing-program-123"),
+        "{:?}",
+        err
+    );
+
+    assert!(opts.spawn(Command::new("cmd /C exit")).is_ok());
+}
+
+#[test]
+pub fn spawn_not_existing_program() {
+    let err = Process::spawn(Command::new("not-existing-program-123 --help")).unwrap_err();
+    assert!(
+        matches!(&err, Error::ProgramNotFound(program) if program == "not-existing-program-123"),
+        "{:?}",
+        err
+    );
+
+    // a directory can't be run
+    let dir = std::env::temp_dir();
+    let err = ProcessOptions::default()
+        .program_path(&dir)
+        .spawn(Command::new("cmd"))
+        .unwrap_err();
+    assert!(
+        matches!(&err, Error::PermissionDenied(program) if program == dir.as_os_str()),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn robust_reads() {
+    for on in [true, false] {
+        let mut proc = ProcessOptions::default()
+            .robust_reads(on)
+            .spawn(Command::new("cmd /C echo Hello World"))
+            .unwrap();
+        let mut reader = proc.output().unwrap();
+
+        assert_eq!(proc.wait(None).unwrap(), 0);
+        drop(proc);
+
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).unwrap();
+        assert!(String::from_utf8_lossy(&buf).contains("Hello World"));
+    }
+}
+
+#[test]
+fn no_inherited_write_ends() {
+    let mut proc = ProcessOptions::default()
+        .robust_reads(false)
+        .spawn(Command::new("cmd /C echo Hello World"))
+        .unwrap();
+    proc.ensure_no_inherited_write_ends().unwrap();
+    let mut reader = proc.output().unwrap();
+
+    // simulate a harness which spawns a long living process inheriting all inheritable handles
+    let mut harness = Command::new("ping")
+        .args(["-n", "10", "127.0.0.1"])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    assert_eq!(proc.wait(None).unwrap(), 0);
+    drop(proc);
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).unwrap();
+        tx.send(buf).unwrap();
+    });
+
+    let buf = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(String::from_utf8_lossy(&buf).contains("Hello World"));
+
+    harness.kill().unwrap();
+    harness.wait().unwrap();
+}
+
+#[test]
+fn headless() {
+    let mut proc = ProcessOptions::default()
+        .headless(120, 40)
+        .spawn(Command::new("cmd /C echo Hello World"))
+        .unwrap();
+    assert!(!proc.vt_enabled());
+
+    let mut reader = proc.output().unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 0);
+    drop(proc);
+
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert!(String::from_utf8_lossy(&buf).contains("Hello World"));
+}
+
+#[test]
+fn exit_code() {
+    let proc = Process::spawn(Command::new("cmd /C exit 3")).unwrap();
+    assert_eq!(proc.exit_code(), None);
+
+    assert_eq!(proc.wait(None).unwrap(), 3);
+    assert_eq!(proc.exit_code(), Some(3));
+    assert_eq!(proc.wait(Some(0)).unwrap(), 3);
+}
+
+#[test]
+fn resize_after_exit() {
+    let mut proc = Process::spawn(Command::new("cmd /C exit")).unwrap();
+    proc.wait(None).unwrap();
+
+    assert!(!proc.is_console_alive());
+    assert!(matches!(proc.resize(120, 40), Err(Error::ConsoleClosed)));
+}
+
+#[test]
+fn scrollback() {
+    let mut proc = ProcessOptions::default()
+        .track_screen(true)
+        .spawn(Command::new(
+            r#"python -c "import time; print('+--+\n|ab|\n+--+', flush=True); time.sleep(5)""#,
+        ))
+        .unwrap();
+
+    let mut reader = BufReader::new(proc.output().unwrap());
+    let mut output = String::new();
+    while !output
+        .split_once("|ab|")
+        .is_some_and(|(_, rest)| rest.contains("+--+"))
+    {
+        assert_ne!(reader.read_line(&mut output).unwrap(), 0);
+    }
+
+    let lines = proc.scrollback().unwrap();
+    let grid = ["+--+", "|ab|", "+--+"];
+    assert!(lines.windows(3).any(|w| w == grid), "{:?}", lines);
+
+    proc.exit(0).unwrap();
+
+    let proc = Process::spawn(Command::new("cmd /C exit")).unwrap();
+    assert_eq!(proc.scrollback(), Err(Error::ScreenNotTracked));
+}
+
+#[test]
+fn wait_status() {
+    let proc = Process::spawn(Command::new("cmd /C exit 0")).unwrap();
+    let status = proc.wait_status().unwrap();
+    assert!(status.success());
+
+    let proc = Process::spawn(Command::new("cmd /C exit 2")).unwrap();
+    let status = proc.wait_status().unwrap();
+    assert_eq!(status.code(), Some(2));
+}
+
+#[test]
+fn prepend_path() {
+    let dir = std::env::temp_dir().join("conpty_prepend_path_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("conpty_test_tool.bat"), "@exit 7").unwrap();
+
+    let proc = ProcessOptions::default()
+        .prepend_path(&dir)
+        .spawn(Command::new("cmd /C conpty_test_tool"))
+        .unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 7);
+
+    let proc = ProcessOptions::default()
+        .append_path(&dir)
+        .spawn(Command::new("cmd /C conpty_test_tool"))
+        .unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 7);
+
+    let proc = Process::spawn(Command::new("cmd /C conpty_test_tool")).unwrap();
+    assert_ne!(proc.wait(None).unwrap(), 7);
+}
+
+#[test]
+fn wait_input_idle_console_process() {
+    let proc = Process::spawn(Command::new("cmd")).unwrap();
+
+    let now = std::time::Instant::now();
+    let _ = proc.wait_input_idle(Duration::from_secs(10));
+    assert!(now.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn options_env() {
+    let batch = r#"cmd /C if "%TEST_ENV%"=="123456" (exit 0) else (exit 1)"#;
+
+    let proc = ProcessOptions::default()
+        .env("TEST_ENV", "123456")
+        .spawn(Command::new(batch))
+        .unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 0);
+
+    // options take precedence over the command
+    let mut cmd = Command::new(batch);
+    cmd.env("TEST_ENV", "NOT_CORRECT_VALUE");
+    let proc = ProcessOptions::default()
+        .envs([("TEST_ENV", "123456")])
+        .spawn(cmd)
+        .unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 0);
+}
+
+#[test]
+fn inherit_parent_env() {
+    std::env::set_var("CONPTY_PARENT_ENV", "1");
+    let batch = r#"cmd /C if "%CONPTY_PARENT_ENV%%TEST_ENV%"=="1123456" (exit 0) else (exit 1)"#;
+
+    let proc = ProcessOptions::default()
+        .inherit_parent_env(true)
+        .env("TEST_ENV", "123456")
+        .spawn(Command::new(batch))
+        .unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 0);
+
+    let proc = ProcessOptions::default()
+        .inherit_parent_env(false)
+        .env("TEST_ENV", "123456")
+        .spawn(Command::new(batch))
+        .unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 1);
+
+    // an empty environment
+    let proc = ProcessOptions::default()
+        .inherit_parent_env(false)
+        .spawn(Command::new(
+            r#"cmd /C if defined CONPTY_PARENT_ENV (exit 1) else (exit 0)"#,
+        ))
+        .unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 0);
+}
+
+#[test]
+fn try_wait() {
+    let mut proc = Process::spawn(Command::new("cmd")).unwrap();
+    assert_eq!(proc.try_wait().unwrap(), None);
+    proc.exit(5).unwrap();
+    proc.wait(None).unwrap();
+    assert_eq!(proc.try_wait().unwrap(), Some(5));
+
+    let proc = Process::spawn(Command::new("cmd /C exit 3")).unwrap();
+    while proc.try_wait().unwrap().is_none() {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(proc.try_wait().unwrap(), Some(3));
+}
+
+#[test]
+fn wait_timeout() {
+    let mut proc = Process::spawn(Command::new("cmd")).unwrap();
+    assert_eq!(proc.wait_timeout(Duration::from_millis(100)).unwrap(), None);
+
+    proc.exit(5).unwrap();
+    assert_eq!(proc.wait_timeout(Duration::from_secs(5)).unwrap(), Some(5));
+}
+
+#[test]
+fn wait_with_times() {
+    let proc = Process::spawn(Command::new("ping -n 2 127.0.0.1")).unwrap();
+    let info = proc.wait_with_times().unwrap();
+    assert_eq!(info.code, 0);
+    assert!(info.wall >= Duration::from_millis(500), "{:?}", info);
+    assert!(info.wall >= info.kernel_time + info.user_time, "{:?}", info);
+}
+
+#[test]
+fn memory_info() {
+    let mut proc = Process::spawn(Command::new("cmd")).unwrap();
+    let info = proc.memory_info().unwrap();
+    assert!(info.working_set > 0, "{:?}", info);
+    assert!(info.peak_working_set >= info.working_set, "{:?}", info);
+    assert!(info.handle_count > 0, "{:?}", info);
+
+    proc.kill().unwrap();
+}
+
+#[test]
+fn kill() {
+    let mut proc = Process::spawn(Command::new("cmd")).unwrap();
+    proc.kill().unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 1);
+
+    // killing an exited process is not an error
+    proc.kill().unwrap();
+}
+
+#[test]
+fn kill_on_drop() {
+    let is_running = |pid: u32| {
+        let output = Command::new("tasklist")
+            .args(["/NH", "/FI", &format!("PID eq {}", pid)])
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).contains(&pid.to_string())
+    };
+
+    // the console is kept open so it's not what terminates the process
+    let proc = ProcessOptions::default()
+        .kill_on_drop(true)
+        .keep_console_on_exit(true)
+        .spawn(Command::new("cmd"))
+        .unwrap();
+    let pid = proc.pid();
+    let _console = proc.resize_handler();
+    assert!(is_running(pid));
+    drop(proc);
+    thread::sleep(Duration::from_millis(500));
+    assert!(!is_running(pid));
+
+    let proc = ProcessOptions::default()
+        .keep_console_on_exit(true)
+        .spawn(Command::new("cmd"))
+        .unwrap();
+    let pid = proc.pid();
+    let _console = proc.resize_handler();
+    drop(proc);
+    thread::sleep(Duration::from_millis(500));
+    assert!(is_running(pid));
+
+    let _ = Command::new("taskkill")
+        .args(["/F", "/PID", &pid.to_string()])
+        .output()
+        .unwrap();
+}
+
+#[test]
+fn wait_deadline() {
+    let mut proc = Process::spawn(Command::new("cmd")).unwrap();
+
+    let deadline = std::time::Instant::now() + Duration::from_millis(100);
+    assert!(matches!(
+        proc.wait_deadline(deadline),
+        Err(Error::Timeout(_))
+    ));
+
+    // a deadline in the past
+    assert!(matches!(
+        proc.wait_deadline(deadline),
+        Err(Error::Timeout(_))
+    ));
+
+    proc.kill().unwrap();
+    let deadline = std::time::Instant::now() + Duration::from_secs(5);
+    assert_eq!(proc.wait_deadline(deadline).unwrap(), 1);
+}
+
+#[test]
+fn send_ctrl_c() {
+    let mut proc = Process::spawn(Command::new("ping -n 30 127.0.0.1")).unwrap();
+    let mut reader = BufReader::new(proc.output().unwrap());
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+
+    proc.send_ctrl_c().unwrap();
+    assert!(proc.wait(Some(5000)).is_ok());
+}
+
+#[test]
+fn shutdown() {
+    let mut proc = Process::spawn(Command::new("ping -n 30 127.0.0.1")).unwrap();
+    let mut reader = BufReader::new(proc.output().unwrap());
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+
+    proc.shutdown(Duration::from_secs(5)).unwrap();
+    assert!(!proc.is_alive());
+
+    // Ctrl-C is ignored so the process is terminated
+    let mut proc = Process::spawn(Command::new(
+        r#"python -c "import signal, time; signal.signal(signal.SIGINT, signal.SIG_IGN); print('ready', flush=True); time.sleep(30)""#,
+    ))
+    .unwrap();
+    let mut reader = BufReader::new(proc.output().unwrap());
+    let mut line = String::new();
+    while !line.contains("ready") {
+        reader.read_line(&mut line).unwrap();
+    }
+
+    assert_eq!(proc.shutdown(Duration::from_millis(500)).unwrap(), 1);
+    assert!(!proc.is_alive());
+}
+
+#[test]
+fn send_ctrl_break() {
+    // ping handles Ctrl-Break by printing statistics so python is used
+    let mut proc = Process::spawn(Command::new(
+        r#"python -c "import time; print('ready', flush=True); time.sleep(30)""#,
+    ))
+    .unwrap();
+    let mut reader = BufReader::new(proc.output().unwrap());
+    let mut line = String::new();
+    while !line.contains("ready") {
+        reader.read_line(&mut line).unwrap();
+    }
+
+    proc.send_ctrl_break().unwrap();
+    assert!(proc.wait(Some(5000)).is_ok());
+}
+
+#[test]
+fn send_ctrl_c_after_console_closed() {
+    let mut proc = Process::spawn(Command::new("cmd")).unwrap();
+    proc.close_console();
+
+    assert!(matches!(proc.send_ctrl_c(), Err(Error::InputClosed)));
+}
+
+#[test]
+fn options_current_dir() {
+    let dir = std::env::temp_dir();
+    let mut proc = ProcessOptions::default()
+        .current_dir(&dir)
+        .spawn(Command::new("cmd /C echo %CD%"))
+        .unwrap();
+    let mut reader = proc.output().unwrap();
+    proc.wait(None).unwrap();
+    drop(proc);
+
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    let output = String::from_utf8_lossy(&buf).to_lowercase();
+    let dir = dir.to_string_lossy().trim_end_matches('\\').to_lowercase();
+    assert!(output.contains(&dir), "{:?}", output);
+
+    let result = ProcessOptions::default()
+        .current_dir(r"C:\conpty\not\existing\dir")
+        .spawn(Command::new("cmd"));
+    assert!(matches!(result, Err(Error::CurrentDirNotFound(_))));
+}
+
+#[test]
+fn resize_size() {
+    let mut proc = ProcessOptions::default()
+        .set_console_size(Some((80, 25)))
+        .spawn(Command::new("cmd"))
+        .unwrap();
+    assert_eq!(proc.size(), (80, 25));
+
+    proc.resize(120, 40).unwrap();
+    assert_eq!(proc.size(), (120, 40));
+    assert_eq!(proc.resize_handler().size(), (120, 40));
+
+    assert!(matches!(proc.resize(0, 40), Err(Error::InvalidSize(0, 40))));
+    assert!(matches!(
+        proc.resize(120, -1),
+        Err(Error::InvalidSize(120, -1))
+    ));
+    assert_eq!(proc.size(), (120, 40));
+}
+
+#[test]
+fn options_size() {
+    let proc = ProcessOptions::default()
+        .size(120, 40)
+        .spawn(Command::new("cmd"))
+        .unwrap();
+    assert_eq!(proc.size(), (120, 40));
+
+    // a size which doesn't fit into i16 is clamped
+    let proc = ProcessOptions::default()
+        .size(u16::MAX, 40)
+        .spawn(Command::new("cmd"))
+        .unwrap();
+    assert_eq!(proc.size(), (i16::MAX, 40));
+
+    let result = ProcessOptions::default()
+        .size(0, 40)
+        .spawn(Command::new("cmd"));
+    assert!(matches!(result, Err(Error::InvalidSize(0, 40))));
+}
+
+#[test]
+fn separate_stderr() {
+    let mut proc = ProcessOptions::default()
+        .separate_stderr(true)
+        .spawn(Command::new("cmd /C echo out && echo err 1>&2"))
+        .unwrap();
+    let mut stderr = proc.stderr().unwrap().unwrap();
+    let code = proc.wait(None).unwrap();
+    assert_eq!(code, 0);
+
+    let mut buf = Vec::new();
+    stderr.read_to_end(&mut buf).unwrap();
+    assert_eq!(String::from_utf8_lossy(&buf).trim(), "err");
+
+    let mut proc = conpty::spawn("echo 1").unwrap();
+    assert!(proc.stderr().unwrap().is_none());
+}
+
+#[test]
+fn track_host_resize() {
+    let console = match conpty::console::Console::current() {
+        Ok(console) => console,
+        // there's no console to track
+        Err(_) => return,
+    };
+    let (columns, rows) = console.window_size().unwrap();
+
+    let mut proc = ProcessOptions::default()
+        .set_console_size(Some((columns as i16 + 1, rows as i16 + 1)))
+        .spawn(Command::new("cmd"))
+        .unwrap();
+    let watcher = proc.track_host_resize().unwrap();
+    thread::sleep(Duration::from_millis(500));
+    assert!(watcher.is_running());
+    assert_eq!(proc.size(), (columns as i16, rows as i16));
+
+    drop(proc);
+    thread::sleep(Duration::from_millis(500));
+    assert!(!watcher.is_running());
+}
+
+#[test]
+fn inherit_from_console() {
+    let console = match conpty::console::Console::current() {
+        Ok(console) => console,
+        // there's no console to inherit from
+        Err(_) => return,
+    };
+    let (columns, rows) = match console.window_size() {
+        Ok(size) => size,
+        // stdout is redirected
+        Err(_) => return,
+    };
+
+    let proc = ProcessOptions::default()
+        .size(columns + 1, rows + 1)
+        .inherit_from(&console)
+        .spawn(Command::new("cmd"))
+        .unwrap();
+    assert_eq!(proc.size(), (columns as i16, rows as i16));
+}
+
+#[test]
+fn vt_enabled() {
+    let proc = Process::spawn(Command::new("cmd /C exit 0")).unwrap();
+    match proc.vt_processing() {
+        // a host which supports ConPTY supports VT processing,
+        // so it's enabled whenever there's a console
+        Ok(on) => {
+            assert!(proc.vt_enabled());
+            assert!(on);
+        }
+        Err(err) => {
+            assert_eq!(err, Error::NoConsoleAttached);
+            assert!(!proc.vt_enabled());
+        }
+    }
+
+    let proc = ProcessOptions::default()
+        .headless(80, 25)
+        .spawn(Command::new("cmd /C exit 0"))
+        .unwrap();
+    assert!(!proc.vt_enabled());
+}
+
+#[test]
+fn enable_host_vt() {
+    let proc = ProcessOptions::default()
+        .enable_host_vt(false)
+        .spawn(Command::new("cmd"))
+        .unwrap();
+    assert!(!proc.vt_enabled());
+}
+
+#[test]
+fn inherit_size() {
+    let proc = ProcessOptions::default()
+        .inherit_size(false)
+        .spawn(Command::new("cmd"))
+        .unwrap();
+    assert_eq!(proc.size(), (80, 25));
+
+    let proc = ProcessOptions::default()
+        .inherit_size(false)
+        .size(100, 30)
+        .spawn(Command::new("cmd"))
+        .unwrap();
+    assert_eq!(proc.size(), (100, 30));
+}
+
+#[test]
+fn process_handle() {
+    use std::os::windows::io::AsRawHandle;
+    use windows::Win32::{
+        Foundation::{HANDLE, WAIT_OBJECT_0},
+        System::Threading::{GetProcessId, WaitForSingleObject},
+    };
+
+    let proc = Process::spawn(Command::new("cmd /C exit 0")).unwrap();
+    let handle = proc.process_handle().try_clone_to_owned().unwrap();
+    let raw = HANDLE(handle.as_raw_handle() as isize);
+    assert_eq!(unsafe { GetProcessId(raw) }, proc.pid());
+
+    drop(proc);
+    // the cloned handle outlives the process
+    assert_eq!(unsafe { WaitForSingleObject(raw, 5000) }, WAIT_OBJECT_0);
+}
+
+#[test]
+fn use_job_object() {
+    let marker = std::env::temp_dir().join("conpty_use_job_object_marker");
+    let _ = std::fs::remove_file(&marker);
+
+    // a grandchild creates a marker file unless it's terminated
+    let command = format!(
+        "cmd /C start /B cmd /C \"ping -n 3 127.0.0.1 >nul & echo done > {}\" & ping -n 30 127.0.0.1 >nul",
+        marker.display()
+    );
+    let mut proc = ProcessOptions::default()
+        .use_job_object(true)
+        .spawn(Command::new(command))
+        .unwrap();
+    thread::sleep(Duration::from_millis(500));
+    proc.kill().unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 1);
+
+    thread::sleep(Duration::from_secs(4));
+    assert!(!marker.exists());
+}
+
+#[test]
+fn spawn_argv() {
+    use std::ffi::{OsStr, OsString};
+
+    let args = [
+        "-c",
+        "import sys; print(sys.argv[1:])",
+        "a b",
+        r#"a"b"#,
+        r"a\ ",
+        "",
+    ]
+    .map(OsString::from);
+    let mut proc = Process::spawn_argv(OsStr::new("python"), &args).unwrap();
+    let (code, output) = proc.run_to_completion().unwrap();
+    assert_eq!(code, 0);
+
+    let output = String::from_utf8_lossy(&output);
+    assert!(
+        output.contains(r#"['a b', 'a"b', 'a\\ ', '']"#),
+        "{:?}",
+        output
+    );
+}
+
+#[test]
+fn args_with_spaces() {
+    let mut cmd = Command::new("python");
+    cmd.args([
+        "-c",
+        "import sys; print('args:', len(sys.argv[1:]))",
+        "hello world",
+    ]);
+
+    let mut proc = Process::spawn(cmd).unwrap();
+    let (code, output) = proc.run_to_completion().unwrap();
+    assert_eq!(code, 0);
+    assert!(String::from_utf8_lossy(&output).contains("args: 1"));
+}
+
+#[test]
+fn creation_flags() {
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+    const CREATE_NEW_CONSOLE: u32 = 0x0000_0010;
+
+    let proc = ProcessOptions::default()
+        .creation_flags(CREATE_NEW_PROCESS_GROUP)
+        .spawn(Command::new("cmd /C exit 0"))
+        .unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 0);
+
+    let result = ProcessOptions::default()
+        .creation_flags(CREATE_NEW_PROCESS_GROUP)
+        .creation_flags(CREATE_NEW_CONSOLE)
+        .spawn(Command::new("cmd"));
+    assert!(matches!(
+        result,
+        Err(Error::InvalidCreationFlags(CREATE_NEW_CONSOLE))
+    ));
+}
+
+#[test]
+fn as_user() {
+    use std::os::windows::io::{FromRawHandle, OwnedHandle};
+    use windows::Win32::{
+        Foundation::HANDLE,
+        Security::{TOKEN_ASSIGN_PRIMARY, TOKEN_DUPLICATE, TOKEN_QUERY},
+        System::Threading::{GetCurrentProcess, OpenProcessToken},
+    };
+
+    // the caller's own primary token doesn't require any privileges
+    let mut token = HANDLE::default();
+    let access = TOKEN_ASSIGN_PRIMARY | TOKEN_DUPLICATE | TOKEN_QUERY;
+    unsafe { OpenProcessToken(GetCurrentProcess(), access, &mut token).unwrap() };
+    let token = unsafe { OwnedHandle::from_raw_handle(token.0 as _) };
+
+    let mut proc = ProcessOptions::default()
+        .as_user(token)
+        .spawn(Command::new("cmd /C echo Hello World"))
+        .unwrap();
+    let mut reader = proc.output().unwrap();
+    assert_eq!(proc.wait(None).unwrap(), 0);
+    drop(proc);
+
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert!(String::from_utf8_lossy(&buf).contains("Hello World"));
+}
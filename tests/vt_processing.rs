@@ -0,0 +1,28 @@
+// The test changes the VT mode of the host console which is shared by the whole test binary,
+// so it's kept in its own binary to not race with tests which check `Process::vt_enabled`.
+
+use std::process::Command;
+
+use conpty::{error::Error, Process};
+
+#[test]
+fn vt_processing() {
+    let proc = Process::spawn(Command::new("cmd")).unwrap();
+    let enabled = match proc.vt_processing() {
+        Ok(enabled) => enabled,
+        Err(err) => {
+            assert_eq!(err, Error::NoConsoleAttached);
+            assert!(!proc.vt_enabled());
+            return;
+        }
+    };
+    assert_eq!(enabled, proc.vt_enabled());
+
+    proc.set_vt_processing(false).unwrap();
+    assert!(!proc.vt_processing().unwrap());
+
+    proc.set_vt_processing(true).unwrap();
+    assert!(proc.vt_processing().unwrap());
+
+    proc.set_vt_processing(enabled).unwrap();
+}